@@ -0,0 +1,237 @@
+use cli::package::{Object, META_SECTION_NAME};
+use colored::*;
+use error::DefaultResult;
+use serde_json::{self, Value};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fs,
+    path::{Path, PathBuf},
+};
+
+/// The environment variable telling the test runner where to record zome function calls
+pub const COVERAGE_ENV_VAR: &str = "HC_COVERAGE_FILE";
+
+pub const COVERAGE_FILE_NAME: &str = "coverage.log";
+
+/// The test artifact with the recorder in front of it, written to the dist folder
+pub const INSTRUMENTED_ARTIFACT_FILE_NAME: &str = "coverage-bundle.js";
+
+/// Starts the lines the recorder prints for each zome function call
+pub const CALL_MARKER: &str = "__hc_coverage__ ";
+
+/// Wraps the apps of hcshell's `Container.loadAndInstantiate` to print each call
+const RECORDER: &str = include_str!("coverage/recorder.js");
+
+/// Writes a copy of a JavaScript test artifact that prints the zome function calls made by
+/// the tests, each on a line starting with `CALL_MARKER`
+pub fn instrument_artifact(artifact: &Path, instrumented: &Path) -> DefaultResult<()> {
+    let source = fs::read_to_string(artifact)?;
+
+    fs::write(instrumented, format!("{}\n{}", RECORDER, source))?;
+
+    Ok(())
+}
+
+const CAPABILITIES_KEY: &str = "capabilities";
+const FUNCTIONS_KEY: &str = "functions";
+const NAME_KEY: &str = "name";
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ZomeCoverage {
+    pub functions: BTreeSet<String>,
+    pub called: BTreeSet<String>,
+}
+
+impl ZomeCoverage {
+    pub fn uncovered(&self) -> Vec<&String> {
+        self.functions.difference(&self.called).collect()
+    }
+}
+
+/// Tracks which of the zome functions defined in a bundle got called during a test run
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Coverage {
+    pub zomes: BTreeMap<String, ZomeCoverage>,
+}
+
+impl Coverage {
+    /// Collects the zome functions out of a bundle file built by `hc package`
+    pub fn from_bundle_file(path: &PathBuf) -> DefaultResult<Coverage> {
+        ensure!(
+            path.exists(),
+            "bundle file {:?} is needed to compute the coverage, but doesn't exist",
+            path
+        );
+
+        let bundle: Object = serde_json::from_str(&fs::read_to_string(path)?)?;
+
+        Ok(Coverage::from_bundle(&bundle))
+    }
+
+    /// Every object in the bundle with a "capabilities" section is a zome: the packager
+    /// inserted the output of its `__hdk_get_json_definition` call there
+    pub fn from_bundle(bundle: &Object) -> Coverage {
+        let mut coverage = Coverage::default();
+        coverage.collect_zomes(bundle);
+        coverage
+    }
+
+    fn collect_zomes(&mut self, tree: &Object) {
+        for (name, node) in tree {
+            if name == META_SECTION_NAME {
+                continue;
+            }
+
+            if let Value::Object(node) = node {
+                if let Some(Value::Object(capabilities)) = node.get(CAPABILITIES_KEY) {
+                    let functions = capabilities
+                        .values()
+                        .filter_map(|capability| capability.get(FUNCTIONS_KEY))
+                        .filter_map(|functions| functions.as_array())
+                        .flat_map(|functions| functions.iter())
+                        .filter_map(|function| function.get(NAME_KEY))
+                        .filter_map(|name| name.as_str())
+                        .map(String::from)
+                        .collect();
+
+                    self.zomes.insert(
+                        name.clone(),
+                        ZomeCoverage {
+                            functions,
+                            called: BTreeSet::new(),
+                        },
+                    );
+                } else {
+                    self.collect_zomes(node);
+                }
+            }
+        }
+    }
+
+    /// Records the calls written by the test runner, one per line as either
+    /// `zome/function` or `zome/capability/function`. Calls to unknown zomes or
+    /// functions are ignored.
+    pub fn record_calls(&mut self, log: &str) {
+        for line in log.lines().map(|line| line.trim()).filter(|line| !line.is_empty()) {
+            let segments: Vec<_> = line.split('/').collect();
+
+            if segments.len() < 2 {
+                continue;
+            }
+
+            let zome = segments[0];
+            let function = segments[segments.len() - 1];
+
+            if let Some(zome_coverage) = self.zomes.get_mut(zome) {
+                if zome_coverage.functions.contains(function) {
+                    zome_coverage.called.insert(function.to_string());
+                }
+            }
+        }
+    }
+
+    /// Whether any call to one of the zome functions was recorded
+    pub fn has_calls(&self) -> bool {
+        self.zomes.values().any(|zome| !zome.called.is_empty())
+    }
+
+    /// The percentage of all zome functions that got called, 100 if there are none
+    pub fn percentage(&self) -> f64 {
+        let (called, total) = self.zomes.values().fold((0, 0), |(called, total), zome| {
+            (called + zome.called.len(), total + zome.functions.len())
+        });
+
+        if total == 0 {
+            100.0
+        } else {
+            called as f64 * 100.0 / total as f64
+        }
+    }
+
+    pub fn print_report(&self) {
+        log!("{} of zome functions:", "Coverage".green().bold());
+
+        for (zome, zome_coverage) in &self.zomes {
+            log!(
+                "  {}: {}/{} functions called",
+                zome,
+                zome_coverage.called.len(),
+                zome_coverage.functions.len()
+            );
+
+            for function in zome_coverage.uncovered() {
+                log!("    {} {}", "uncovered".yellow(), function);
+            }
+        }
+
+        log!("  total: {:.1}%", self.percentage());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bundle() -> Object {
+        let bundle = json!({
+            "name": "Holochain App Name",
+            "zomes": {
+                "blog": {
+                    "description": "The blog App",
+                    "code": { "code": "AGFzbQEAAAA=" },
+                    "capabilities": {
+                        "main": {
+                            "capability": { "membrane": "public" },
+                            "functions": [
+                                { "name": "create_post", "inputs": [], "outputs": [] },
+                                { "name": "get_post", "inputs": [], "outputs": [] }
+                            ]
+                        },
+                        "admin": {
+                            "capability": { "membrane": "agent" },
+                            "functions": [
+                                { "name": "delete_post", "inputs": [], "outputs": [] }
+                            ]
+                        }
+                    }
+                },
+                "users": {
+                    "code": { "code": "AGFzbQEAAAA=" },
+                    "capabilities": {}
+                }
+            }
+        });
+
+        bundle.as_object().unwrap().clone()
+    }
+
+    #[test]
+    fn collects_zome_functions_from_bundle() {
+        let coverage = Coverage::from_bundle(&bundle());
+
+        assert_eq!(coverage.zomes.len(), 2);
+        assert_eq!(
+            coverage.zomes["blog"].functions,
+            ["create_post", "delete_post", "get_post"]
+                .iter()
+                .map(|name| name.to_string())
+                .collect()
+        );
+        assert!(coverage.zomes["users"].functions.is_empty());
+    }
+
+    #[test]
+    fn records_calls_and_computes_percentage() {
+        let mut coverage = Coverage::from_bundle(&bundle());
+
+        coverage.record_calls("blog/main/create_post\nblog/create_post\n\nblog/unknown\nchat/send\n");
+
+        assert_eq!(coverage.zomes["blog"].uncovered(), vec!["delete_post", "get_post"]);
+        assert!((coverage.percentage() - 100.0 / 3.0).abs() < 1e-9);
+
+        coverage.record_calls("blog/admin/delete_post\nblog/main/get_post");
+
+        assert!(coverage.zomes["blog"].uncovered().is_empty());
+        assert_eq!(coverage.percentage(), 100.0);
+    }
+}
@@ -0,0 +1,24 @@
+// Prepended to the test artifact by `hc test --coverage`: prints a line for each zome
+// function the tests call through an app of `Container.loadAndInstantiate`, which hc
+// picks out of the runner's output to compute the coverage.
+(function (global) {
+  var container = global.Container
+
+  if (!container || typeof container.loadAndInstantiate !== 'function') {
+    return
+  }
+
+  var loadAndInstantiate = container.loadAndInstantiate
+
+  container.loadAndInstantiate = function () {
+    var app = loadAndInstantiate.apply(container, arguments)
+    var call = app.call
+
+    app.call = function (zome, capability, fn) {
+      console.log('__hc_coverage__ ' + zome + '/' + capability + '/' + fn)
+      return call.apply(app, arguments)
+    }
+
+    return app
+  }
+})(this);
@@ -0,0 +1,506 @@
+use cli::package::{build_files, PROJECT_CONFIG_FILE_NAME};
+use colored::*;
+use config_files::{Build, Project};
+use error::{DefaultResult, HolochainError};
+use semver::Version;
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    path::{Path, PathBuf},
+    process::Command,
+};
+use util;
+
+pub const WASM_TARGET: &str = "wasm32-unknown-unknown";
+
+const NODE_MODULES_DIR_NAME: &str = "node_modules";
+
+/// The tools whose version gets asked for with `--version`, other commands might not
+/// understand it
+const VERSIONED_TOOLS: &[&str] = &["cargo", "rustup", "node", "npm", "wasm-opt"];
+
+/// The oldest versions the scaffolded zomes and tests work with: edition 2018 of the Rust
+/// zomes needs cargo 1.31, the package-lock.json of the tests npm 5 and node 8
+const MINIMUM_VERSIONS: &[(&str, &str)] = &[("cargo", "1.31.0"), ("node", "8.0.0"), ("npm", "5.0.0")];
+
+/// Something the build or the tests of the project need
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+enum Tool {
+    /// A command, looked up on the PATH or, if it's a path, relative to the folder it's run in
+    Executable { bin: String, base_path: PathBuf },
+    /// A target installed with rustup
+    RustTarget(String),
+    /// wasm-opt, which only gets run if it's installed
+    WasmOpt,
+}
+
+impl Tool {
+    fn executable<P: Into<PathBuf>>(bin: &str, base_path: P) -> Tool {
+        // commands on the PATH are the same wherever they are run
+        let base_path = if Path::new(bin).components().count() > 1 {
+            base_path.into()
+        } else {
+            PathBuf::new()
+        };
+
+        Tool::Executable { bin: bin.to_string(), base_path }
+    }
+
+    fn name(&self) -> String {
+        match self {
+            Tool::Executable { bin, base_path } if base_path.as_os_str().is_empty() => bin.clone(),
+            Tool::Executable { bin, base_path } => base_path.join(bin).display().to_string(),
+            Tool::RustTarget(target) => format!("rust target {}", target),
+            Tool::WasmOpt => "wasm-opt".into(),
+        }
+    }
+
+    /// Looks for the tool and returns its version, if it has one, or `None` if it's missing
+    fn find(&self) -> Option<String> {
+        match self {
+            Tool::Executable { bin, base_path } => {
+                let path = util::find_executable(base_path, bin)?;
+
+                Some(if VERSIONED_TOOLS.contains(&bin.as_str()) {
+                    version(&path).unwrap_or_default()
+                } else {
+                    String::new()
+                })
+            }
+            Tool::RustTarget(target) => {
+                let output = Command::new("rustup")
+                    .args(&["target", "list", "--installed"])
+                    .output()
+                    .ok()?;
+
+                String::from_utf8_lossy(&output.stdout)
+                    .lines()
+                    .find(|line| line.trim() == target)
+                    .map(|_| String::new())
+            }
+            Tool::WasmOpt => util::find_executable(Path::new(""), "wasm-opt")
+                .map(|path| version(&path).unwrap_or_default()),
+        }
+    }
+
+    /// The oldest version of the tool that works, if there is one
+    fn minimum_version(&self) -> Option<Version> {
+        match self {
+            Tool::Executable { bin, base_path } if base_path.as_os_str().is_empty() => MINIMUM_VERSIONS
+                .iter()
+                .find(|(name, _)| name == bin)
+                .and_then(|(_, minimum)| Version::parse(minimum).ok()),
+            _ => None,
+        }
+    }
+
+    /// What to do if the version of the tool is older than the given one
+    fn update_fix(&self, minimum: &Version) -> String {
+        match self {
+            Tool::Executable { bin, .. } if bin == "cargo" => {
+                format!("update Rust to {} or newer with `rustup update`", minimum)
+            }
+            Tool::Executable { bin, .. } if bin == "npm" => {
+                format!("update npm to {} or newer with `npm install -g npm`", minimum)
+            }
+            Tool::Executable { bin, .. } if bin == "node" => {
+                format!("update Node.js to {} or newer, see https://nodejs.org", minimum)
+            }
+            _ => format!("update {} to {} or newer", self.name(), minimum),
+        }
+    }
+
+    /// What to do if the tool is missing
+    fn fix(&self) -> String {
+        match self {
+            Tool::Executable { bin, base_path } if base_path.as_os_str().is_empty() => {
+                match bin.as_str() {
+                    "cargo" | "rustc" | "rustup" => {
+                        "install Rust with rustup, see https://rustup.rs".into()
+                    }
+                    "node" | "npm" => "install Node.js, which comes with npm, see https://nodejs.org".into(),
+                    "hcshell" => {
+                        "install hcshell, see https://github.com/holochain/holosqape".into()
+                    }
+                    _ => format!("make sure {} is installed and on your PATH", bin),
+                }
+            }
+            Tool::Executable { bin, base_path } => {
+                if Path::new(bin).iter().any(|component| component == NODE_MODULES_DIR_NAME) {
+                    format!("run `npm install` in {}", base_path.display())
+                } else {
+                    format!("make sure {} exists and is executable", self.name())
+                }
+            }
+            Tool::RustTarget(target) => format!("run `rustup target add {}`", target),
+            Tool::WasmOpt => "install binaryen, see https://github.com/WebAssembly/binaryen, \
+                              or the artifact doesn't get optimized with wasm-opt"
+                .into(),
+        }
+    }
+}
+
+/// The result of looking for one of the tools
+#[derive(Clone, Debug, Serialize)]
+pub struct Check {
+    pub tool: String,
+    /// The config files that need the tool
+    pub needed_by: Vec<String>,
+    pub found: bool,
+    /// The first line the tool prints for `--version`, if it's asked for it
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub version: Option<String>,
+    /// Whether the version is older than the oldest one that works
+    pub outdated: bool,
+    /// Optional tools only get warned about
+    pub optional: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fix: Option<String>,
+}
+
+/// Returns the first line a tool prints for `--version`
+fn version(path: &Path) -> Option<String> {
+    let output = Command::new(path).arg("--version").output().ok()?;
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+
+    stdout
+        .lines()
+        .chain(stderr.lines())
+        .map(|line| line.trim())
+        .find(|line| !line.is_empty())
+        .map(|line| line.to_string())
+}
+
+/// Finds the version number in what a tool prints for `--version`, like `v10.15.0` or
+/// `cargo 1.31.0 (339d9f9c8 2018-11-16)`
+fn parse_version(version: &str) -> Option<Version> {
+    version
+        .split_whitespace()
+        .filter_map(|word| Version::parse(word.trim_start_matches('v')).ok())
+        .next()
+}
+
+/// Determines which tools the builds of all profiles and the tests of the project need
+fn requirements(path: &PathBuf) -> DefaultResult<BTreeMap<Tool, BTreeSet<String>>> {
+    let mut requirements: BTreeMap<Tool, BTreeSet<String>> = BTreeMap::new();
+
+    {
+        let mut require = |tool: Tool, needed_by: &str| {
+            if let Tool::Executable { ref bin, .. } = tool {
+                // npm is a script run by node
+                if bin == "npm" {
+                    requirements
+                        .entry(Tool::executable("node", ""))
+                        .or_default()
+                        .insert(needed_by.to_string());
+                }
+            }
+
+            requirements.entry(tool).or_default().insert(needed_by.to_string());
+        };
+
+        for build_file in build_files(path)? {
+            let build = Build::from_file(&build_file)?;
+            let base_path = build_file.parent().unwrap_or(path);
+            let needed_by = build_file.strip_prefix(path)?.display().to_string();
+
+            let builds = Some(build.clone())
+                .into_iter()
+                .chain(build.profiles.keys().map(|profile| build.for_profile(profile)));
+
+            for build in builds {
+                for (bin, step) in &build.steps {
+                    require(Tool::executable(bin, base_path), &needed_by);
+
+                    if bin == "cargo" && step.args().iter().any(|arg| arg.contains(WASM_TARGET)) {
+                        require(Tool::executable("rustup", ""), &needed_by);
+                        require(Tool::RustTarget(WASM_TARGET.into()), &needed_by);
+                    }
+                }
+
+                if build.optimize.map(|optimize| optimize.wasm_opt.is_some()).unwrap_or(false) {
+                    require(Tool::WasmOpt, &needed_by);
+                }
+            }
+        }
+
+        let test_config = Project::load(path)?.test;
+        let needed_by = format!("the test config in {}", PROJECT_CONFIG_FILE_NAME);
+
+        for step in &test_config.prep {
+            require(Tool::executable(&step.bin, path.join(&test_config.dir)), &needed_by);
+        }
+
+        require(Tool::executable(&test_config.runner.bin, path), &needed_by);
+    }
+
+    Ok(requirements)
+}
+
+/// Looks for the tools the project at the given path needs
+pub fn check(path: &PathBuf) -> DefaultResult<Vec<Check>> {
+    let checks = requirements(path)?
+        .into_iter()
+        .map(|(tool, needed_by)| {
+            let version = tool.find();
+
+            // versions that can't be read are given the benefit of the doubt
+            let too_old = tool.minimum_version().filter(|minimum| {
+                version
+                    .as_ref()
+                    .and_then(|version| parse_version(version))
+                    .map_or(false, |version| version < *minimum)
+            });
+
+            let fix = if version.is_none() {
+                Some(tool.fix())
+            } else {
+                too_old.as_ref().map(|minimum| tool.update_fix(minimum))
+            };
+
+            Check {
+                tool: tool.name(),
+                needed_by: needed_by.into_iter().collect(),
+                found: version.is_some(),
+                version: version.filter(|version| !version.is_empty()),
+                outdated: too_old.is_some(),
+                optional: tool == Tool::WasmOpt,
+                fix,
+            }
+        })
+        .collect();
+
+    Ok(checks)
+}
+
+/// Checks the tools the project needs and tells how to install the missing ones
+pub fn doctor(path: &PathBuf) -> DefaultResult<Vec<Check>> {
+    let checks = check(path)?;
+
+    for check in &checks {
+        let status = if check.found && !check.outdated {
+            "ok".green().bold()
+        } else if check.optional {
+            "warning".yellow().bold()
+        } else if check.outdated {
+            "outdated".red().bold()
+        } else {
+            "missing".red().bold()
+        };
+
+        let version = check
+            .version
+            .as_ref()
+            .map(|version| format!(" ({})", version))
+            .unwrap_or_default();
+
+        log!("{} {}{}, needed by {}", status, check.tool, version, check.needed_by.join(", "));
+
+        if let Some(ref fix) = check.fix {
+            log!("    {}", fix);
+        }
+    }
+
+    let missing = checks
+        .iter()
+        .filter(|check| (!check.found || check.outdated) && !check.optional)
+        .count();
+
+    if missing > 0 {
+        return Err(HolochainError::Toolchain {
+            message: format!(
+                "{} of the required tools {} missing or outdated, see the fixes above",
+                missing,
+                if missing == 1 { "is" } else { "are" }
+            ),
+            checks,
+        }.into());
+    }
+
+    log!("{} all the required tools are installed", "Done:".green().bold());
+
+    Ok(checks)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use assert_cmd::prelude::*;
+    use cli::{APP_CONFIG_FILE_NAME, BUILD_CONFIG_FILE_NAME};
+    use config_files::{Optimize, Step};
+    use serde_json;
+    use std::{fs, process::Command};
+    use tempfile::TempDir;
+    use test_utils::gen_dir;
+
+    /// A project with a single zome built by the given build file, tested with `sh`
+    fn gen_project(build: Build) -> TempDir {
+        let dir = gen_dir();
+        let code_path = dir.path().join("zomes/blog/code");
+        fs::create_dir_all(&code_path).unwrap();
+
+        build.save_as(code_path.join(BUILD_CONFIG_FILE_NAME)).unwrap();
+        fs::write(dir.path().join(APP_CONFIG_FILE_NAME), "{}").unwrap();
+
+        let mut project = Project::default();
+        project.test.prep = vec![Step::new("sh", &["-c", "true"])];
+        project.test.runner = Step::new("sh", &[]);
+
+        fs::write(
+            dir.path().join(PROJECT_CONFIG_FILE_NAME),
+            serde_json::to_string(&project).unwrap(),
+        ).unwrap();
+
+        dir
+    }
+
+    #[test]
+    fn finds_the_tools_of_all_profiles() {
+        let mut debug = Build::with_artifact("blog.wasm").cmd("hc-missing-debug-tool", &[]);
+        debug.optimize = Some(Optimize {
+            wasm_opt: Some(vec!["-O".into()]),
+            ..Optimize::default()
+        });
+
+        let dir = gen_project(
+            Build::with_artifact("blog.wasm")
+                .cmd("./node_modules/.bin/asc", &["index.ts"])
+                .profile("debug", debug),
+        );
+
+        let checks = check(&dir.path().to_path_buf()).unwrap();
+        let find = |tool: &str| checks.iter().find(|check| check.tool.ends_with(tool)).unwrap();
+
+        let asc = find("node_modules/.bin/asc");
+        assert!(!asc.found);
+        assert_eq!(asc.needed_by, vec!["zomes/blog/code/.build"]);
+        assert!(asc.fix.as_ref().unwrap().starts_with("run `npm install` in"));
+
+        let debug_tool = find("hc-missing-debug-tool");
+        assert!(!debug_tool.found);
+        assert_eq!(
+            debug_tool.fix,
+            Some("make sure hc-missing-debug-tool is installed and on your PATH".into())
+        );
+
+        assert!(find("wasm-opt").optional);
+
+        let sh = find("sh");
+        assert!(sh.found);
+        assert_eq!(sh.fix, None);
+        assert_eq!(sh.needed_by, vec!["the test config in .hcconfig"]);
+    }
+
+    #[test]
+    fn requires_the_wasm_target() {
+        let dir = gen_project(Build::with_artifact("blog.wasm").cmd(
+            "cargo",
+            &["build", "--release", "--target=wasm32-unknown-unknown"],
+        ));
+
+        let checks = check(&dir.path().to_path_buf()).unwrap();
+        let tools: Vec<_> = checks.iter().map(|check| check.tool.as_str()).collect();
+
+        assert_eq!(tools, vec!["cargo", "rustup", "sh", "rust target wasm32-unknown-unknown"]);
+
+        let target = checks.last().unwrap();
+        if !target.found {
+            assert_eq!(
+                target.fix,
+                Some("run `rustup target add wasm32-unknown-unknown`".into())
+            );
+        }
+    }
+
+    #[test]
+    fn parses_versions() {
+        assert_eq!(parse_version("v10.15.0"), Some(Version::new(10, 15, 0)));
+        assert_eq!(parse_version("6.4.1"), Some(Version::new(6, 4, 1)));
+        assert_eq!(
+            parse_version("cargo 1.31.0 (339d9f9c8 2018-11-16)"),
+            Some(Version::new(1, 31, 0))
+        );
+        assert_eq!(parse_version("wasm-opt version 87"), None);
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn fails_if_tools_are_outdated() {
+        use std::{env, os::unix::fs::PermissionsExt};
+
+        let dir = gen_project(Build::with_artifact("blog.wasm"));
+        let mut project = Project::load(&dir.path().to_path_buf()).unwrap();
+        project.test.runner = Step::new("node", &[]);
+        fs::write(
+            dir.path().join(PROJECT_CONFIG_FILE_NAME),
+            serde_json::to_string(&project).unwrap(),
+        ).unwrap();
+
+        // a node that is too old, ahead of the real one on the PATH
+        let bin_path = dir.path().join("bin");
+        fs::create_dir_all(&bin_path).unwrap();
+        let node_path = bin_path.join("node");
+        fs::write(&node_path, "#!/bin/sh\necho v6.11.0\n").unwrap();
+        fs::set_permissions(&node_path, fs::Permissions::from_mode(0o755)).unwrap();
+        let path = env::join_paths(
+            Some(bin_path).into_iter().chain(env::split_paths(&env::var_os("PATH").unwrap())),
+        ).unwrap();
+
+        let output = Command::main_binary()
+            .unwrap()
+            .current_dir(dir.path())
+            .env("PATH", path)
+            .args(&["doctor"])
+            .output()
+            .unwrap();
+
+        let stdout = String::from_utf8_lossy(&output.stdout);
+
+        assert_eq!(output.status.code(), Some(8));
+        assert!(stdout.contains("outdated node (v6.11.0)"));
+        assert!(stdout.contains("update Node.js to 8.0.0 or newer, see https://nodejs.org"));
+        assert!(String::from_utf8_lossy(&output.stderr)
+            .contains("1 of the required tools is missing or outdated"));
+    }
+
+    #[test]
+    fn fails_if_tools_are_missing() {
+        let dir = gen_project(Build::with_artifact("blog.wasm").cmd("hc-missing-tool", &[]));
+
+        // run from the zome, the project gets found above it
+        let output = Command::main_binary()
+            .unwrap()
+            .current_dir(dir.path().join("zomes/blog/code"))
+            .args(&["doctor"])
+            .output()
+            .unwrap();
+
+        let stdout = String::from_utf8_lossy(&output.stdout);
+
+        assert_eq!(output.status.code(), Some(8));
+        assert!(stdout.contains("make sure hc-missing-tool is installed and on your PATH"));
+        assert!(String::from_utf8_lossy(&output.stderr).contains("1 of the required tools is missing"));
+
+        // the checks come with the error, for editors to show what to fix
+        let output = Command::main_binary()
+            .unwrap()
+            .current_dir(dir.path())
+            .args(&["--output", "json", "doctor"])
+            .output()
+            .unwrap();
+
+        assert_eq!(output.status.code(), Some(8));
+
+        let result: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+        assert_eq!(result["error"]["kind"], "toolchain");
+
+        let checks = result["error"]["checks"].as_array().unwrap();
+        let missing = checks
+            .iter()
+            .find(|check| check["tool"] == "hc-missing-tool")
+            .unwrap();
+        assert_eq!(missing["found"], false);
+        assert_eq!(missing["fix"], "make sure hc-missing-tool is installed and on your PATH");
+    }
+}
@@ -1,9 +1,16 @@
 use cli::{
+    init::APP_CONFIG_FILE_NAME,
     package::CODE_DIR_NAME,
-    scaffold::{self, Scaffold},
+    scaffold::{
+        self,
+        plugin::{self, LanguageSource},
+        Scaffold,
+    },
 };
-use error::DefaultResult;
-use serde_json;
+use colored::*;
+use config_files::App as AppConfig;
+use error::{DefaultResult, HolochainError};
+use serde_json::{self, Value};
 use std::{
     fs::{self, File},
     path::PathBuf,
@@ -12,64 +19,202 @@ use util;
 
 pub const ZOME_CONFIG_FILE_NAME: &str = "zome.json";
 
-pub fn generate(zome_name: &PathBuf, language: &str) -> DefaultResult<()> {
-    if !zome_name.exists() {
-        fs::create_dir_all(&zome_name)?;
+/// The built-in languages zomes can be generated in, the first one is the default. More
+/// can be added as plugins, see `scaffold::plugin`.
+pub const SUPPORTED_LANGUAGES: &[&str] = &["rust", "assemblyscript", "c"];
+
+/// Words that can't be used as a zome name, since the name ends up as the name of a
+/// crate and in identifiers: the Rust keywords and the names of the built-in crates
+const RESERVED_NAMES: &[&str] = &[
+    "abstract", "alloc", "as", "async", "await", "become", "box", "break", "const", "continue",
+    "core", "crate", "do", "dyn", "else", "enum", "extern", "false", "final", "fn", "for", "if",
+    "impl", "in", "let", "loop", "macro", "match", "mod", "move", "mut", "override", "priv",
+    "proc_macro", "pub", "ref", "return", "self", "Self", "static", "std", "struct", "super",
+    "test", "trait", "true", "try", "type", "typeof", "unsafe", "unsized", "use", "virtual",
+    "where", "while", "yield",
+];
+
+/// Ensures the name is usable as an identifier in Rust (and thus in AssemblyScript), the
+/// kind of name is used in the error messages
+pub fn validate_identifier(kind: &str, name: &str) -> DefaultResult<()> {
+    let mut chars = name.chars();
+
+    ensure!(
+        chars
+            .next()
+            .map(|c| c.is_ascii_alphabetic() || c == '_')
+            .unwrap_or(false),
+        "{} name {:?} has to start with a letter or an underscore",
+        kind,
+        name
+    );
+    ensure!(
+        chars.all(|c| c.is_ascii_alphanumeric() || c == '_'),
+        "{} name {:?} may only contain letters, digits and underscores",
+        kind,
+        name
+    );
+    ensure!(
+        name != "_" && !RESERVED_NAMES.contains(&name),
+        "{} name {:?} is reserved in Rust",
+        kind,
+        name
+    );
+
+    Ok(())
+}
+
+/// Ensures the name is usable as a Rust crate name and identifier
+pub fn validate_zome_name(name: &str) -> DefaultResult<()> {
+    validate_identifier("zome", name)
+}
+
+/// Determines the name of the zome at the given path: the given name if there is one,
+/// otherwise the last component of the path, with dashes turned into underscores
+pub fn zome_name(zome_path: &PathBuf, name: Option<&str>) -> DefaultResult<String> {
+    let zome_name = match name {
+        Some(name) => name.to_string(),
+        None => {
+            let file_name = match zome_path.file_name() {
+                Some(_) => util::file_name_string(zome_path)?,
+                // paths like "." or ".." only have a name once resolved
+                None => util::file_name_string(&zome_path.canonicalize()?)?,
+            };
+
+            file_name.replace("-", "_")
+        }
+    };
+
+    validate_zome_name(&zome_name)
+        .map_err(|err| format_err!("{}, use --name to choose a different one", err))?;
+
+    Ok(zome_name)
+}
+
+/// The authors listed in the app.json of the project the zome is part of, if there is one
+fn project_authors(zome_path: &PathBuf) -> DefaultResult<Vec<String>> {
+    let zome_path = zome_path.canonicalize()?;
+
+    match zome_path
+        .ancestors()
+        .map(|dir| dir.join(APP_CONFIG_FILE_NAME))
+        .find(|app_config_path| app_config_path.is_file())
+    {
+        Some(app_config_path) => {
+            let app_config: AppConfig = serde_json::from_str(&fs::read_to_string(&app_config_path)?)
+                .map_err(|err| HolochainError::config(&app_config_path, err))?;
+
+            Ok(app_config
+                .authors
+                .iter()
+                .map(|author| author.identifier().to_string())
+                .collect())
+        }
+        None => Ok(Vec::new()),
+    }
+}
+
+/// Generates a zome in the given folder, named after the folder unless a name is given.
+/// Only runs the package manager of the language if `install` is set, so that zomes
+/// can be generated offline.
+pub fn generate(
+    zome_path: &PathBuf,
+    language: &str,
+    name: Option<&str>,
+    install: bool,
+) -> DefaultResult<()> {
+    let language = plugin::find_language(language)?;
+
+    let zome_name = zome_name(zome_path, name)?;
+
+    if !zome_path.exists() {
+        fs::create_dir_all(&zome_path)?;
     }
 
     ensure!(
-        zome_name.is_dir(),
+        zome_path.is_dir(),
         "argument \"zome_name\" doesn't point to a directory"
     );
 
-    let file_name = util::file_name_string(&zome_name)?;
-
     let zome_config_json = json!{
         {
-            "description": format!("The {} App", file_name)
+            "description": format!("The {} App", zome_name)
         }
     };
 
-    let file = File::create(zome_name.join(ZOME_CONFIG_FILE_NAME))?;
+    let file = File::create(zome_path.join(ZOME_CONFIG_FILE_NAME))?;
     serde_json::to_writer_pretty(file, &zome_config_json)?;
 
-    let code_dir = zome_name.join(CODE_DIR_NAME);
+    let code_dir = zome_path.join(CODE_DIR_NAME);
     fs::create_dir_all(&code_dir)?;
-    let zome_name_string = zome_name.to_str()
-        .expect("Invalid zome path given")
-        .to_string()
-        .replace("/", "_")
-        .replace("zomes_", "");
-
-    // match against all supported languages
-    match language {
-        "rust" => scaffold(&scaffold::rust::RustScaffold::new(zome_name_string), code_dir)?,
-        "assemblyscript" => scaffold(&scaffold::assemblyscript::AssemblyScriptScaffold::new(), code_dir)?,
-        // TODO: supply zome name for AssemblyScriptScaffold as well
-        _ => bail!("unsupported language: {}", language),
+
+    match language.source {
+        LanguageSource::BuiltIn => match language.name.as_str() {
+            "rust" => {
+                let authors = project_authors(zome_path)?;
+                scaffold(&scaffold::rust::RustScaffold::new(zome_name, authors), code_dir, install)?
+            }
+            "assemblyscript" => scaffold(&scaffold::assemblyscript::AssemblyScriptScaffold::new(zome_name), code_dir, install)?,
+            "c" => scaffold(&scaffold::c::CScaffold::new(zome_name), code_dir, install)?,
+            _ => bail!("unsupported language: {}", language.name),
+        },
+        LanguageSource::Template(template_path) => {
+            scaffold(&plugin::TemplateScaffold::new(template_path, zome_name), code_dir, install)?
+        }
+        LanguageSource::Executable(executable_path) => {
+            scaffold(&plugin::ExecutableScaffold::new(executable_path, zome_name), code_dir, install)?
+        }
     }
 
     Ok(())
 }
 
-fn scaffold<S: Scaffold>(tooling: &S, base_path: PathBuf) -> DefaultResult<()> {
-    tooling.gen(base_path)
+/// Prints the languages zomes can be generated in, and where they come from, and returns
+/// them for `--output json`
+pub fn list_languages() -> DefaultResult<Vec<Value>> {
+    let mut languages = Vec::new();
+
+    for language in plugin::languages()? {
+        let source = match language.source {
+            LanguageSource::BuiltIn => "built-in".to_string(),
+            LanguageSource::Template(ref path) | LanguageSource::Executable(ref path) => {
+                format!("{}", path.display())
+            }
+        };
+
+        let description = language.description();
+
+        match description {
+            Some(ref description) => log!("{} - {} ({})", language.name.green().bold(), description, source),
+            None => log!("{} ({})", language.name.green().bold(), source),
+        }
+
+        languages.push(json!({
+            "name": language.name,
+            "description": description,
+            "source": source,
+        }));
+    }
+
+    Ok(languages)
+}
+
+fn scaffold<S: Scaffold>(tooling: &S, base_path: PathBuf, install: bool) -> DefaultResult<()> {
+    tooling.gen(&base_path)?;
+
+    if install {
+        tooling.install(&base_path)?;
+    }
+
+    Ok(())
 }
 
 #[cfg(test)]
 mod tests {
+    use super::*;
     use assert_cmd::prelude::*;
     use std::process::Command;
-    use tempfile::{Builder, TempDir};
-
-    const HOLOCHAIN_TEST_PREFIX: &str = "org.holochain.test";
-
-    fn gen_dir() -> TempDir {
-        Builder::new()
-            .prefix(HOLOCHAIN_TEST_PREFIX)
-            .tempdir()
-            .unwrap()
-    }
+    use test_utils::gen_dir;
 
     #[test]
     fn can_generate_scaffolds() {
@@ -98,4 +243,18 @@ mod tests {
          //   .success();
 
     }
+
+    #[test]
+    fn zome_name_test() {
+        assert_eq!(zome_name(&PathBuf::from("zomes/blog"), None).unwrap(), "blog");
+        assert_eq!(zome_name(&PathBuf::from("./zomes/blog/"), None).unwrap(), "blog");
+        assert_eq!(zome_name(&PathBuf::from("/tmp/app/zomes/blog"), None).unwrap(), "blog");
+        assert_eq!(zome_name(&PathBuf::from("zomes/chat-room"), None).unwrap(), "chat_room");
+        assert_eq!(zome_name(&PathBuf::from("zomes/2fa"), Some("two_factor")).unwrap(), "two_factor");
+
+        assert!(zome_name(&PathBuf::from("zomes/2fa"), None).is_err());
+        assert!(zome_name(&PathBuf::from("zomes/blog.v2"), None).is_err());
+        assert!(zome_name(&PathBuf::from("zomes/blog"), Some("match")).is_err());
+        assert!(zome_name(&PathBuf::from("zomes/test"), None).is_err());
+    }
 }
@@ -0,0 +1,542 @@
+use cli::{
+    generate::validate_identifier,
+    package::CODE_DIR_NAME,
+    scaffold::{assemblyscript::TYPESCRIPT_FILE_NAME, rust::LIB_RS_PATH},
+    init::ZOMES_DIR_NAME,
+};
+use colored::*;
+use config_files::Project;
+use error::DefaultResult;
+use std::{fs, path::PathBuf};
+use util;
+
+pub const TEST_INDEX_FILE_NAME: &str = "index.js";
+
+const RUST_DEFAULT_OUTPUT: &str = "serde_json::Value";
+const TYPESCRIPT_DEFAULT_OUTPUT: &str = "string";
+
+const INDENT: &str = "    ";
+
+/// The source file of a zome that gets extended, depending on the language of the zome
+enum ZomeSource {
+    Rust(PathBuf),
+    AssemblyScript(PathBuf),
+}
+
+/// A `name:Type` pair, as given for function inputs and entry fields
+struct Field {
+    name: String,
+    ty: String,
+}
+
+fn parse_fields(fields: &[String]) -> DefaultResult<Vec<Field>> {
+    fields
+        .iter()
+        .map(|field| {
+            let mut parts = field.splitn(2, ':');
+            let name = parts.next().unwrap_or_default().trim().to_string();
+            let ty = parts.next().unwrap_or_default().trim().to_string();
+
+            ensure!(!ty.is_empty(), "{:?} has to be given as name:Type", field);
+            validate_identifier("field", &name)?;
+
+            Ok(Field { name, ty })
+        }).collect()
+}
+
+/// Finds the zome given either as a path or by its name inside the zomes folder of the project
+fn resolve_zome(project_root: &PathBuf, zome: &PathBuf) -> DefaultResult<PathBuf> {
+    let zome_path = if zome.is_dir() {
+        zome.clone()
+    } else {
+        project_root.join(ZOMES_DIR_NAME).join(zome)
+    };
+
+    ensure!(
+        zome_path.join(CODE_DIR_NAME).is_dir(),
+        "{:?} is not a zome with a {} folder",
+        zome,
+        CODE_DIR_NAME
+    );
+
+    Ok(zome_path)
+}
+
+fn zome_source(zome_path: &PathBuf) -> DefaultResult<ZomeSource> {
+    let code_path = zome_path.join(CODE_DIR_NAME);
+
+    if code_path.join(LIB_RS_PATH).is_file() {
+        Ok(ZomeSource::Rust(code_path.join(LIB_RS_PATH)))
+    } else if code_path.join(TYPESCRIPT_FILE_NAME).is_file() {
+        Ok(ZomeSource::AssemblyScript(code_path.join(TYPESCRIPT_FILE_NAME)))
+    } else {
+        bail!(
+            "unable to find the source of zome {:?}, expected either {} or {}",
+            zome_path,
+            LIB_RS_PATH,
+            TYPESCRIPT_FILE_NAME
+        )
+    }
+}
+
+fn indent(text: &str, level: usize) -> String {
+    let prefix = INDENT.repeat(level);
+
+    text.lines()
+        .map(|line| {
+            if line.is_empty() {
+                String::new()
+            } else {
+                format!("{}{}", prefix, line)
+            }
+        }).collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Returns the positions right after the opening bracket following `marker`, and of the
+/// matching closing bracket
+fn find_block(source: &str, marker: &str, open: char, close: char) -> Option<(usize, usize)> {
+    let marker_start = source.find(marker)?;
+    let content_start = marker_start + source[marker_start..].find(open)? + 1;
+
+    let mut depth = 1;
+
+    for (offset, c) in source[content_start..].char_indices() {
+        if c == open {
+            depth += 1;
+        } else if c == close {
+            depth -= 1;
+
+            if depth == 0 {
+                return Some((content_start, content_start + offset));
+            }
+        }
+    }
+
+    None
+}
+
+/// Finds the position of `name` followed by `followed_by`, where `name` is a whole
+/// identifier and not the end of a longer one
+fn find_identifier(source: &str, name: &str, followed_by: &str) -> Option<usize> {
+    source
+        .match_indices(&format!("{}{}", name, followed_by))
+        .map(|(index, _)| index)
+        .find(|index| {
+            source[..*index]
+                .chars()
+                .last()
+                .map(|c| !(c.is_alphanumeric() || c == '_'))
+                .unwrap_or(true)
+        })
+}
+
+/// The indentation level of the line containing the given position
+fn indent_level_at(source: &str, position: usize) -> usize {
+    let line_start = source[..position].rfind('\n').map(|i| i + 1).unwrap_or(0);
+    let spaces = source[line_start..]
+        .chars()
+        .take_while(|c| *c == ' ')
+        .count();
+
+    spaces / INDENT.len()
+}
+
+/// Appends an item to the block following `marker`, e.g. `entries: [ ... ]`, separating it
+/// from existing items with the given separator
+fn insert_into_block(
+    source: &str,
+    marker: &str,
+    open: char,
+    close: char,
+    separator: &str,
+    item: &str,
+) -> DefaultResult<String> {
+    let (content_start, content_end) = find_block(source, marker, open, close)
+        .ok_or_else(|| format_err!("unable to find \"{}\" in the zome source", marker))?;
+
+    let level = indent_level_at(source, content_start);
+    let content = source[content_start..content_end].trim_end();
+    let item = indent(item, level + 1);
+
+    let new_content = if content.trim().is_empty() {
+        format!("\n{}\n{}", item, INDENT.repeat(level))
+    } else {
+        format!("{}{}\n{}\n{}", content, separator, item, INDENT.repeat(level))
+    };
+
+    Ok(format!(
+        "{}{}{}",
+        &source[..content_start],
+        new_content,
+        &source[content_end..]
+    ))
+}
+
+/// Inserts top level code right before the `define_zome!` invocation
+fn insert_before_define_zome(source: &str, code: &str) -> DefaultResult<String> {
+    let position = source
+        .find("define_zome!")
+        .ok_or_else(|| format_err!("unable to find define_zome! in the zome source"))?;
+
+    Ok(format!("{}{}\n\n{}", &source[..position], code, &source[position..]))
+}
+
+fn camel_case(name: &str) -> String {
+    name.split('_')
+        .map(|part| {
+            let mut chars = part.chars();
+            match chars.next() {
+                Some(first) => first.to_uppercase().chain(chars).collect(),
+                None => String::new(),
+            }
+        }).collect()
+}
+
+fn typescript_default_value(ty: &str) -> &'static str {
+    match ty {
+        "string" => "\"\"",
+        "bool" => "false",
+        "i8" | "i16" | "i32" | "i64" | "u8" | "u16" | "u32" | "u64" | "f32" | "f64" => "0",
+        _ => "unreachable()",
+    }
+}
+
+fn rust_function(source: &str, name: &str, capability: &str, inputs: &[Field], output: &str) -> DefaultResult<String> {
+    let params = inputs
+        .iter()
+        .map(|input| format!("{}: {}", input.name, input.ty))
+        .collect::<Vec<_>>()
+        .join(", ");
+
+    let handler = format!(
+        "fn handle_{}({}) -> {} {{\n{}unimplemented!()\n}}",
+        name, params, output, INDENT
+    );
+    let source = insert_before_define_zome(source, &handler)?;
+
+    let function = format!(
+        "{}: {{\n{indent}inputs: |{}|,\n{indent}outputs: |result: {}|,\n{indent}handler: handle_{}\n}}",
+        name,
+        params,
+        output,
+        name,
+        indent = INDENT
+    );
+
+    let (functions_start, functions_end) = find_block(&source, "functions:", '{', '}')
+        .ok_or_else(|| format_err!("unable to find \"functions:\" in the zome source"))?;
+    let functions = &source[functions_start..functions_end];
+
+    if let Some(capability_start) = find_identifier(functions, capability, " (") {
+        // add to the existing capability, starting at its line so that its indentation is kept
+        let capability_start = functions_start + capability_start;
+        let capability_start = source[..capability_start].rfind('\n').map(|i| i + 1).unwrap_or(0);
+        let updated = insert_into_block(
+            &source[capability_start..functions_end],
+            capability,
+            '{',
+            '}',
+            "",
+            &function,
+        )?;
+
+        Ok(format!(
+            "{}{}{}",
+            &source[..capability_start],
+            updated,
+            &source[functions_end..]
+        ))
+    } else {
+        let capability_block = format!("{} (Public) {{\n{}\n}}", capability, indent(&function, 1));
+
+        insert_into_block(&source, "functions:", '{', '}', "", &capability_block)
+    }
+}
+
+fn rust_entry(source: &str, name: &str, fields: &[Field]) -> DefaultResult<String> {
+    let type_name = camel_case(name);
+
+    let struct_fields = fields
+        .iter()
+        .map(|field| format!("{}pub {}: {},", INDENT, field.name, field.ty))
+        .collect::<Vec<_>>()
+        .join("\n");
+    let entry_struct = format!(
+        "#[derive(Serialize, Deserialize, Debug, Clone)]\npub struct {} {{\n{}\n}}",
+        type_name, struct_fields
+    );
+    let source = insert_before_define_zome(source, &entry_struct)?;
+
+    let entry = format!(
+        "entry!(
+    name: \"{name}\",
+    description: \"\",
+    sharing: hdk::holochain_core_types::dna::zome::entry_types::Sharing::Public,
+    native_type: {type_name},
+
+    validation_package: || {{
+        hdk::ValidationPackageDefinition::Entry
+    }},
+
+    validation: |_{name}: {type_name}, _ctx: hdk::ValidationData| {{
+        Ok(())
+    }}
+)",
+        name = name,
+        type_name = type_name
+    );
+
+    insert_into_block(&source, "entries:", '[', ']', ",", &entry)
+}
+
+fn typescript_function(source: &str, name: &str, capability: &str, inputs: &[Field], output: &str) -> String {
+    let params = inputs
+        .iter()
+        .map(|input| format!("{}: {}", input.name, input.ty))
+        .collect::<Vec<_>>()
+        .join(", ");
+
+    format!(
+        "{}\n// capability: {}\n@zome_function\nfunction {}({}): {} {{\n{indent}// TODO: implement {}\n{indent}return {}\n}}\n",
+        source.trim_end(),
+        capability,
+        name,
+        params,
+        output,
+        name,
+        typescript_default_value(output),
+        indent = INDENT
+    )
+}
+
+fn typescript_entry(source: &str, name: &str, fields: &[Field]) -> String {
+    let class_fields = fields
+        .iter()
+        .map(|field| format!("{}{}: {}", INDENT, field.name, field.ty))
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    format!(
+        "{}\n\n@can_stringify\nclass {} {{\n{}\n}}\n",
+        source.trim_end(),
+        camel_case(name),
+        class_fields
+    )
+}
+
+/// Appends a test stub for the new function or entry to the index file of the test folder,
+/// named `zome/item` so that `hc test --zome` selects it
+fn add_test_stub(project_root: &PathBuf, zome_path: &PathBuf, item: &str, todo: &str) -> DefaultResult<()> {
+    let test_config = Project::load(project_root)?.test;
+    let test_index_path = project_root.join(&test_config.dir).join(TEST_INDEX_FILE_NAME);
+
+    if !test_index_path.is_file() {
+        log!(
+            "{} no test stub added, since there is no {:?}",
+            "Skipping:".yellow().bold(),
+            test_index_path
+        );
+        return Ok(());
+    }
+
+    let zome_name = util::file_name_string(&zome_path.canonicalize()?)?;
+    let test_stub = format!(
+        "\ntest('{}/{}', (t) => {{\n  // TODO: {}\n  t.end()\n}})\n",
+        zome_name, item, todo
+    );
+
+    let mut test_index = fs::read_to_string(&test_index_path)?;
+    test_index.push_str(&test_stub);
+    fs::write(&test_index_path, test_index)?;
+
+    Ok(())
+}
+
+/// Adds a zome function, with a handler stub, to the given zome
+pub fn function(
+    project_root: &PathBuf,
+    zome: &PathBuf,
+    name: &str,
+    capability: &str,
+    inputs: &[String],
+    output: Option<&str>,
+) -> DefaultResult<()> {
+    validate_identifier("function", name)?;
+    validate_identifier("capability", capability)?;
+
+    let zome_path = resolve_zome(project_root, zome)?;
+    let inputs = parse_fields(inputs)?;
+
+    match zome_source(&zome_path)? {
+        ZomeSource::Rust(path) => {
+            let source = fs::read_to_string(&path)?;
+            let output = output.unwrap_or(RUST_DEFAULT_OUTPUT);
+            fs::write(&path, rust_function(&source, name, capability, &inputs, output)?)?;
+        }
+        ZomeSource::AssemblyScript(path) => {
+            let source = fs::read_to_string(&path)?;
+            let output = output.unwrap_or(TYPESCRIPT_DEFAULT_OUTPUT);
+            fs::write(&path, typescript_function(&source, name, capability, &inputs, output))?;
+        }
+    }
+
+    add_test_stub(
+        project_root,
+        &zome_path,
+        name,
+        &format!("call {} and check the result", name),
+    )?;
+
+    log!(
+        "{} function {} in zome {:?}",
+        "Generated".green().bold(),
+        name,
+        zome_path
+    );
+
+    Ok(())
+}
+
+/// Adds an entry type, with a native type holding the given fields, to the given zome
+pub fn entry(project_root: &PathBuf, zome: &PathBuf, name: &str, fields: &[String]) -> DefaultResult<()> {
+    validate_identifier("entry", name)?;
+
+    let zome_path = resolve_zome(project_root, zome)?;
+    let fields = parse_fields(fields)?;
+
+    match zome_source(&zome_path)? {
+        ZomeSource::Rust(path) => {
+            let source = fs::read_to_string(&path)?;
+            fs::write(&path, rust_entry(&source, name, &fields)?)?;
+        }
+        ZomeSource::AssemblyScript(path) => {
+            let source = fs::read_to_string(&path)?;
+            fs::write(&path, typescript_entry(&source, name, &fields))?;
+        }
+    }
+
+    add_test_stub(
+        project_root,
+        &zome_path,
+        name,
+        &format!("commit a {} entry and check that it can be retrieved", name),
+    )?;
+
+    log!(
+        "{} entry {} in zome {:?}",
+        "Generated".green().bold(),
+        name,
+        zome_path
+    );
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fields(fields: &[&str]) -> Vec<Field> {
+        parse_fields(&fields.iter().map(|f| f.to_string()).collect::<Vec<_>>()).unwrap()
+    }
+
+    #[test]
+    fn rust_function_test() {
+        let source = include_str!("scaffold/rust/lib.rs");
+
+        let source = rust_function(source, "create_post", "main", &fields(&["content:String"]), "serde_json::Value").unwrap();
+        let source = rust_function(&source, "get_post", "main", &fields(&["address:String"]), "serde_json::Value").unwrap();
+        let source = rust_function(&source, "delete_post", "admin", &[], "bool").unwrap();
+
+        assert!(source.contains("fn handle_create_post(content: String) -> serde_json::Value {\n    unimplemented!()\n}\n"));
+        assert!(source.contains("fn handle_delete_post() -> bool {\n    unimplemented!()\n}\n\ndefine_zome!"));
+        assert!(source.contains(
+            "    functions: {
+        main (Public) {
+            create_post: {
+                inputs: |content: String|,
+                outputs: |result: serde_json::Value|,
+                handler: handle_create_post
+            }
+            get_post: {
+                inputs: |address: String|,
+                outputs: |result: serde_json::Value|,
+                handler: handle_get_post
+            }
+        }
+        admin (Public) {
+            delete_post: {
+                inputs: ||,
+                outputs: |result: bool|,
+                handler: handle_delete_post
+            }
+        }
+    }
+}"
+        ));
+    }
+
+    #[test]
+    fn rust_function_matches_whole_capability_names() {
+        let source = include_str!("scaffold/rust/lib.rs");
+
+        let source = rust_function(source, "add_domain", "domain", &[], "bool").unwrap();
+        let source = rust_function(&source, "create_post", "main", &[], "bool").unwrap();
+        // "domain (" ends with "main (", which must not count as the main capability
+        let source = rust_function(&source, "get_post", "main", &[], "bool").unwrap();
+
+        assert!(source.contains(
+            "    functions: {
+        domain (Public) {
+            add_domain: {
+                inputs: ||,
+                outputs: |result: bool|,
+                handler: handle_add_domain
+            }
+        }
+        main (Public) {
+            create_post: {
+                inputs: ||,
+                outputs: |result: bool|,
+                handler: handle_create_post
+            }
+            get_post: {
+                inputs: ||,
+                outputs: |result: bool|,
+                handler: handle_get_post
+            }
+        }
+    }
+}"
+        ));
+    }
+
+    #[test]
+    fn rust_entry_test() {
+        let source = include_str!("scaffold/rust/lib.rs");
+
+        let source = rust_entry(source, "blog_post", &fields(&["title:String", "body:String"])).unwrap();
+        let source = rust_entry(&source, "comment", &fields(&["body:String"])).unwrap();
+
+        assert!(source.contains("pub struct BlogPost {\n    pub title: String,\n    pub body: String,\n}"));
+        assert!(source.contains("    entries: [\n        entry!(\n            name: \"blog_post\","));
+        assert!(source.contains("        ),\n        entry!(\n            name: \"comment\","));
+        assert!(source.contains("native_type: Comment,"));
+    }
+
+    #[test]
+    fn typescript_test() {
+        let source = typescript_function("import {}", "create_post", "main", &fields(&["content:string"]), "string");
+        let source = typescript_entry(&source, "blog_post", &fields(&["title:string"]));
+
+        assert!(source.contains("@zome_function\nfunction create_post(content: string): string {\n    // TODO: implement create_post\n    return \"\"\n}"));
+        assert!(source.contains("@can_stringify\nclass BlogPost {\n    title: string\n}"));
+    }
+
+    #[test]
+    fn parse_fields_test() {
+        assert!(parse_fields(&["title".to_string()]).is_err());
+        assert!(parse_fields(&["2title:String".to_string()]).is_err());
+    }
+}
@@ -1,119 +1,593 @@
 use colored::*;
-use config_files::App as AppConfig;
+use config_files::{App as AppConfig, Author, Project};
+use cli::generate::{generate, validate_zome_name, SUPPORTED_LANGUAGES};
+use cli::project_template::{self, TemplateVariables};
+use cli::scaffold::{
+    plugin,
+    rust::{find_workspace, CARGO_FILE_NAME, TARGET_DIR_NAME},
+};
+use cli::templates::Templates;
 use cli::package::{
+    CODE_DIR_NAME,
     GITIGNORE_FILE_NAME,
     IGNORE_FILE_NAME,
-    DEFAULT_BUNDLE_FILE_NAME
+    DEFAULT_BUNDLE_FILE_NAME,
+    PROJECT_CONFIG_FILE_NAME,
 };
 use cli::test::{
     TEST_DIR_NAME,
     DIST_DIR_NAME,
 };
 use error::DefaultResult;
-use serde_json;
+use semver::Version;
+use serde_json::{self, Value};
+use util;
 use std::{
-    fs::{self, File, OpenOptions},
-    path::{PathBuf},
-    io::Write,
+    fs,
+    io::BufRead,
+    path::{Path, PathBuf},
+    str::FromStr,
 };
 
-fn create_test_file(test_folder_path: &PathBuf, test_file_name: &str, test_file_contents: &str) -> DefaultResult<()> {
-    let dest_filepath = test_folder_path.join(test_file_name);
-    let mut file = OpenOptions::new().write(true).create(true).open(dest_filepath)?;
-    file.write_all(test_file_contents.as_bytes())?;
-    Ok(())
+pub const APP_CONFIG_FILE_NAME: &str = "app.json";
+
+pub const ZOMES_DIR_NAME: &str = "zomes";
+
+/// Creates the files and folders of a new project, never touching any that already
+/// exist, and keeps track of what was created and what was skipped
+#[derive(Debug, Serialize)]
+pub struct InitReport {
+    root: PathBuf,
+    pub created: Vec<PathBuf>,
+    pub skipped: Vec<PathBuf>,
+}
+
+impl InitReport {
+    pub fn new(root: &PathBuf) -> InitReport {
+        InitReport {
+            root: root.clone(),
+            created: Vec::new(),
+            skipped: Vec::new(),
+        }
+    }
+
+    fn record(&mut self, path: &Path, created: bool) {
+        let relative_path = path.strip_prefix(&self.root).unwrap_or(path).to_path_buf();
+
+        if created {
+            self.created.push(relative_path);
+        } else {
+            self.skipped.push(relative_path);
+        }
+    }
+
+    pub fn create_dir<P: AsRef<Path>>(&mut self, path: P) -> DefaultResult<()> {
+        let path = path.as_ref();
+
+        if path.is_dir() {
+            self.record(path, false);
+        } else {
+            fs::create_dir_all(path)?;
+            self.record(path, true);
+        }
+
+        Ok(())
+    }
+
+    pub fn write_file<P: AsRef<Path>, C: AsRef<[u8]>>(&mut self, path: P, contents: C) -> DefaultResult<()> {
+        let path = path.as_ref();
+
+        if path.exists() {
+            self.record(path, false);
+        } else {
+            if let Some(parent) = path.parent() {
+                fs::create_dir_all(parent)?;
+            }
+
+            fs::write(path, contents)?;
+            self.record(path, true);
+        }
+
+        Ok(())
+    }
+
+    pub fn print(&self) {
+        for path in &self.created {
+            log!("  {} {}", "created".green(), path.display());
+        }
+
+        for path in &self.skipped {
+            log!("  {} {} (already exists)", "skipped".yellow(), path.display());
+        }
+    }
 }
 
-fn setup_test_folder(path: &PathBuf, test_folder: &str) -> DefaultResult<()> {
+/// The files of the test folder, by the names of their templates
+const TEST_FOLDER_FILES: &[(&str, &str)] = &[
+    ("index.js", "test/index.js"),
+    ("package-lock.json", "test/package-lock.json"),
+    ("package.json", "test/package.json"),
+    ("README.md", "test/README.md"),
+    ("webpack.config.js", "test/webpack.config.js"),
+];
+
+fn setup_test_folder(
+    report: &mut InitReport,
+    templates: &Templates,
+    data: &Value,
+    path: &PathBuf,
+    test_folder: &str,
+) -> DefaultResult<()> {
     let tests_path = path.join(test_folder);
-    fs::create_dir_all(tests_path.clone())?;
-    create_test_file(&tests_path, "index.js", include_str!("js-tests-scaffold/index.js"))?;
-    create_test_file(&tests_path, "package-lock.json", include_str!("js-tests-scaffold/package-lock.json"))?;
-    create_test_file(&tests_path, "package.json", include_str!("js-tests-scaffold/package.json"))?;
-    create_test_file(&tests_path, "README.md", include_str!("js-tests-scaffold/README.md"))?;
-    create_test_file(&tests_path, "webpack.config.js", include_str!("js-tests-scaffold/webpack.config.js"))?;
+    report.create_dir(&tests_path)?;
+
+    for (file_name, template) in TEST_FOLDER_FILES {
+        report.write_file(tests_path.join(file_name), templates.render(template, data)?)?;
+    }
+
     Ok(())
 }
 
-pub fn init(path: &PathBuf) -> DefaultResult<()> {
-    if !path.exists() {
-        fs::create_dir_all(&path)?;
-    } else {
-        let zomes_dir = fs::read_dir(&path)?;
+const DEFAULT_AUTHOR: &str = "Author Name <author@name.com>";
+
+/// A zome to generate right away when initializing an app, given as `name[:language]`
+#[derive(Clone, Debug, PartialEq)]
+pub struct ZomeDetails {
+    pub name: String,
+    pub language: String,
+}
+
+impl FromStr for ZomeDetails {
+    type Err = String;
+
+    fn from_str(zome: &str) -> Result<Self, Self::Err> {
+        let mut parts = zome.splitn(2, ':');
+        let name = parts.next().unwrap_or_default().trim().to_string();
+        let language = parts.next().unwrap_or(SUPPORTED_LANGUAGES[0]).trim().to_string();
+
+        validate_zome_name(&name).map_err(|err| err.to_string())?;
 
-        if zomes_dir.count() > 0 {
-            bail!("directory is not empty");
+        plugin::find_language(&language).map_err(|err| err.to_string())?;
+
+        Ok(ZomeDetails { name, language })
+    }
+}
+
+/// The values describing a new app, missing ones get filled in with defaults
+#[derive(Clone, Debug, Default)]
+pub struct AppDetails {
+    pub name: Option<String>,
+    pub authors: Vec<String>,
+    pub description: Option<String>,
+    pub version: Option<Version>,
+    pub zomes: Vec<ZomeDetails>,
+}
+
+impl AppDetails {
+    /// The app name, which defaults to the name of the app folder
+    fn name(&self, path: &PathBuf) -> DefaultResult<String> {
+        match self.name {
+            Some(ref name) => Ok(name.clone()),
+            None if path.exists() => util::file_name_string(&path.canonicalize()?),
+            None => util::file_name_string(path),
         }
     }
 
+    fn authors(&self) -> Vec<String> {
+        if self.authors.is_empty() {
+            vec![DEFAULT_AUTHOR.to_string()]
+        } else {
+            self.authors.clone()
+        }
+    }
+
+    fn description(&self) -> String {
+        self.description
+            .clone()
+            .unwrap_or_else(|| AppConfig::default().description)
+    }
+
+    fn version(&self) -> Version {
+        self.version
+            .clone()
+            .unwrap_or_else(|| AppConfig::default().version)
+    }
+
+    fn variables(&self, path: &PathBuf) -> DefaultResult<TemplateVariables> {
+        Ok(TemplateVariables {
+            name: self.name(path)?,
+            author: self.authors().join(", "),
+            description: self.description(),
+        })
+    }
+
+    /// The values available to the templates of the scaffolded files
+    fn template_data(&self, path: &PathBuf) -> DefaultResult<Value> {
+        let zomes: Vec<_> = self
+            .zomes
+            .iter()
+            .map(|zome| json!({ "name": zome.name, "language": zome.language }))
+            .collect();
+
+        Ok(json!({
+            "name": self.name(path)?,
+            "description": self.description(),
+            "authors": self.authors(),
+            "version": self.version().to_string(),
+            "zomes": zomes,
+        }))
+    }
+
+    fn app_config(&self, path: &PathBuf) -> DefaultResult<AppConfig> {
+        Ok(AppConfig {
+            name: self.name(path)?,
+            description: self.description(),
+            authors: self.authors().into_iter().map(Author::new).collect(),
+            version: self.version(),
+            ..AppConfig::default()
+        })
+    }
+
+    /// Asks for each of the values on the terminal, suggesting the ones already given or
+    /// the defaults, and then for any number of zomes to generate
+    pub fn prompt<R: BufRead>(self, path: &PathBuf, input: &mut R) -> DefaultResult<AppDetails> {
+        let name = util::prompt(input, "App name", &self.name(path)?)?;
+        let description = util::prompt(input, "Description", &self.description())?;
+        let authors = util::prompt(input, "Authors, separated by commas", &self.authors().join(", "))?
+            .split(',')
+            .map(|author| author.trim().to_string())
+            .filter(|author| !author.is_empty())
+            .collect();
+
+        let version = loop {
+            let answer = util::prompt(input, "Initial version", &self.version().to_string())?;
+
+            match Version::parse(&answer) {
+                Ok(version) => break version,
+                Err(err) => log!("{} {}", "Invalid version:".red(), err),
+            }
+        };
+
+        let mut zomes = self.zomes.clone();
+
+        loop {
+            let zome_name = util::prompt(input, "Name of a zome to generate, leave empty to finish", "")?;
+
+            if zome_name.is_empty() {
+                break;
+            }
+
+            if let Err(err) = validate_zome_name(&zome_name) {
+                log!("{} {}", "Invalid zome name:".red(), err);
+                continue;
+            }
+
+            let language_names: Vec<_> = plugin::languages()?
+                .into_iter()
+                .map(|language| language.name)
+                .collect();
+
+            let language = loop {
+                let question = format!("Language of zome {} ({})", zome_name, language_names.join(", "));
+                let answer = util::prompt(input, &question, SUPPORTED_LANGUAGES[0])?;
+
+                if language_names.contains(&answer) {
+                    break answer;
+                }
+
+                log!("{} {}", "Unsupported language:".red(), answer);
+            };
+
+            zomes.push(ZomeDetails { name: zome_name, language });
+        }
+
+        Ok(AppDetails {
+            name: Some(name),
+            authors,
+            description: Some(description),
+            version: Some(version),
+            zomes,
+        })
+    }
+}
+
+fn create_app_config(report: &mut InitReport, path: &PathBuf, details: &AppDetails) -> DefaultResult<()> {
+    report.write_file(
+        path.join(APP_CONFIG_FILE_NAME),
+        serde_json::to_string_pretty(&details.app_config(path)?)?,
+    )
+}
+
+/// Creates the default skeleton of a Holochain app
+fn create_skeleton(report: &mut InitReport, path: &PathBuf, details: &AppDetails) -> DefaultResult<()> {
     // create empty zomes folder
-    fs::create_dir_all(path.join("zomes"))?;
+    report.create_dir(path.join(ZOMES_DIR_NAME))?;
 
     // create base DNA json config
-    let app_config_file = File::create(path.join("app.json"))?;
-    serde_json::to_writer_pretty(app_config_file, &AppConfig::default())?;
+    create_app_config(report, path, details)?;
+
+    let templates = Templates::load()?;
+    let data = details.template_data(path)?;
 
     // create a default .gitignore file with good defaults
-    let gitignore_file_path = path.join(GITIGNORE_FILE_NAME);
-    let mut gitignore_file = OpenOptions::new().write(true).create(true).open(gitignore_file_path)?;
-    let gitignore_starter = include_str!("git-scaffold/.gitignore");
-    gitignore_file.write_all(gitignore_starter.as_bytes())?;
+    report.write_file(path.join(GITIGNORE_FILE_NAME), templates.render("gitignore", &data)?)?;
 
     // create a default .hcignore file with good defaults
     let ignores = [
         &DIST_DIR_NAME,
         &TEST_DIR_NAME,
         &DEFAULT_BUNDLE_FILE_NAME,
-        "README.md"
+        "README.md",
+        &CARGO_FILE_NAME,
+        "Cargo.lock",
+        &TARGET_DIR_NAME,
     ].join("\n");
-    let mut hcignore_file = File::create(path.join(&IGNORE_FILE_NAME))?;
-    hcignore_file.write_all(ignores.as_bytes())?;
+    report.write_file(path.join(&IGNORE_FILE_NAME), ignores)?;
+
+    // create a project config file describing how to run the tests
+    report.write_file(
+        path.join(PROJECT_CONFIG_FILE_NAME),
+        serde_json::to_string_pretty(&Project::default())?,
+    )?;
 
     // create a test folder with useful files
-    setup_test_folder(&path, &TEST_DIR_NAME)?;
+    setup_test_folder(report, &templates, &data, &path, &TEST_DIR_NAME)?;
+
+    Ok(())
+}
+
+/// Creates a Cargo workspace at the root of the app, which the Rust zomes get added to, so
+/// that they share one target folder. Apps that already have Rust zomes outside of a
+/// workspace are left alone, since adding one would break their builds.
+fn create_workspace(report: &mut InitReport, path: &PathBuf) -> DefaultResult<()> {
+    let zomes_path = path.join(ZOMES_DIR_NAME);
+
+    if zomes_path.is_dir() {
+        for entry in fs::read_dir(&zomes_path)? {
+            let code_path = entry?.path().join(CODE_DIR_NAME);
+
+            if code_path.join(CARGO_FILE_NAME).is_file() && find_workspace(&code_path)?.is_none() {
+                return Ok(());
+            }
+        }
+    }
+
+    report.write_file(
+        path.join(CARGO_FILE_NAME),
+        Templates::load()?.render("rust/workspace/Cargo.toml", &json!({}))?,
+    )
+}
 
-    println!(
-        "{} new Holochain project at: {:?}",
-        "Created".green().bold(),
-        path
-    );
+/// Ensures an app can be initialized at the given path: unless `merge` is set, the folder
+/// has to be empty, if it exists
+pub fn check_target(path: &PathBuf, merge: bool) -> DefaultResult<()> {
+    if path.exists() && !merge && fs::read_dir(&path)?.count() > 0 {
+        bail!("directory is not empty, use --merge to only add the missing files");
+    }
 
     Ok(())
 }
 
-#[cfg(test)]
-pub mod tests {
-    use super::*;
-    use tempfile::{Builder, TempDir};
+/// Initializes a new app at the given path, either with the default skeleton or from the
+/// given project template. Unless `merge` is set, the folder has to be empty. Otherwise
+/// only the missing files get created, and existing ones are left as they are. With
+/// `workspace`, the Rust zomes are members of a Cargo workspace at the root of the app.
+pub fn init(
+    path: &PathBuf,
+    template: Option<&str>,
+    details: &AppDetails,
+    merge: bool,
+    workspace: bool,
+) -> DefaultResult<InitReport> {
+    check_target(path, merge)?;
+
+    if !path.exists() {
+        fs::create_dir_all(&path)?;
+    }
+
+    let mut report = InitReport::new(path);
 
-    const HOLOCHAIN_TEST_PREFIX: &str = "org.holochain.test";
+    if let Some(template) = template {
+        let template_path = project_template::resolve_template(template)?;
 
-    fn gen_dir() -> TempDir {
-        Builder::new()
-            .prefix(HOLOCHAIN_TEST_PREFIX)
-            .tempdir()
-            .unwrap()
+        project_template::instantiate(&mut report, &template_path, path, &details.variables(path)?)?;
+
+        // every app needs a DNA config, even if the template doesn't bring one
+        create_app_config(&mut report, path, details)?;
+    } else {
+        create_skeleton(&mut report, path, details)?;
+    }
+
+    if workspace {
+        create_workspace(&mut report, path)?;
+    }
+
+    for zome in &details.zomes {
+        let zome_path = path.join(ZOMES_DIR_NAME).join(&zome.name);
+
+        if zome_path.exists() {
+            report.record(&zome_path, false);
+        } else {
+            generate(&zome_path, &zome.language, None, false)?;
+            report.record(&zome_path, true);
+        }
+    }
+
+    if merge {
+        log!(
+            "{} missing files to the Holochain project at: {:?}",
+            "Added".green().bold(),
+            path
+        );
+        report.print();
+    } else {
+        log!(
+            "{} new Holochain project at: {:?}",
+            "Created".green().bold(),
+            path
+        );
     }
 
+    Ok(report)
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use std::io::Cursor;
+    use test_utils::gen_dir;
+
     #[test]
     fn init_test() {
         let dir = gen_dir();
         let dir_path_buf = &dir.path().to_path_buf();
-        let result = init(dir_path_buf);
+        let result = init(dir_path_buf, None, &AppDetails::default(), false, true);
 
         assert!(result.is_ok());
         assert!(dir_path_buf.join("zomes").exists());
         assert!(dir_path_buf.join("app.json").exists());
         assert!(dir_path_buf.join(IGNORE_FILE_NAME).exists());
         assert!(dir_path_buf.join(GITIGNORE_FILE_NAME).exists());
+        assert!(dir_path_buf.join(PROJECT_CONFIG_FILE_NAME).exists());
         assert!(dir_path_buf.join(TEST_DIR_NAME).exists());
     }
 
+    #[test]
+    fn init_details_test() {
+        let dir = gen_dir();
+        let dir_path_buf = &dir.path().join("chat");
+        let details = AppDetails {
+            name: None,
+            authors: vec!["Jane <jane@example.com>".into()],
+            description: Some("A chat app".into()),
+            version: Some(Version::new(1, 2, 3)),
+            zomes: Vec::new(),
+        };
+
+        init(dir_path_buf, None, &details, false, true).unwrap();
+
+        let app_config: serde_json::Value = serde_json::from_str(
+            &fs::read_to_string(dir_path_buf.join(APP_CONFIG_FILE_NAME)).unwrap(),
+        ).unwrap();
+
+        assert_eq!(app_config["name"], "chat");
+        assert_eq!(app_config["description"], "A chat app");
+        assert_eq!(app_config["authors"][0]["indentifier"], "Jane <jane@example.com>");
+        assert_eq!(app_config["version"], "1.2.3");
+    }
+
+    #[test]
+    fn prompt_details_test() {
+        let dir = gen_dir();
+        let dir_path_buf = &dir.path().join("chat");
+        let mut input = Cursor::new(
+            "\n\nJane <jane@example.com>, Joe\nfoo\n0.2.0\nusers\nklingon\n\nposts\nassemblyscript\n\n",
+        );
+
+        let details = AppDetails::default().prompt(dir_path_buf, &mut input).unwrap();
+
+        assert_eq!(details.name, Some("chat".into()));
+        assert_eq!(details.description, Some(AppConfig::default().description));
+        assert_eq!(details.authors, vec!["Jane <jane@example.com>", "Joe"]);
+        assert_eq!(details.version, Some(Version::new(0, 2, 0)));
+        assert_eq!(
+            details.zomes,
+            vec![
+                ZomeDetails { name: "users".into(), language: "rust".into() },
+                ZomeDetails { name: "posts".into(), language: "assemblyscript".into() },
+            ]
+        );
+    }
+
+    #[test]
+    fn zome_details_from_str_test() {
+        assert_eq!(
+            "users".parse(),
+            Ok(ZomeDetails { name: "users".into(), language: "rust".into() })
+        );
+        assert_eq!(
+            "posts:assemblyscript".parse(),
+            Ok(ZomeDetails { name: "posts".into(), language: "assemblyscript".into() })
+        );
+        assert!("posts:klingon".parse::<ZomeDetails>().is_err());
+        assert!(":rust".parse::<ZomeDetails>().is_err());
+        assert!("my-zome".parse::<ZomeDetails>().is_err());
+    }
+
+    #[test]
+    fn init_from_template_test() {
+        let template = gen_dir();
+        let template_path = template.path().to_path_buf();
+        fs::create_dir_all(template_path.join("zomes")).unwrap();
+        fs::write(template_path.join("README.md"), "# <<APP_NAME>>").unwrap();
+
+        let dir = gen_dir();
+        let dir_path_buf = &dir.path().to_path_buf();
+        let details = AppDetails {
+            name: Some("chat".into()),
+            ..AppDetails::default()
+        };
+
+        init(dir_path_buf, template_path.to_str(), &details, false, true).unwrap();
+
+        assert!(dir_path_buf.join("zomes").exists());
+        assert!(dir_path_buf.join(APP_CONFIG_FILE_NAME).exists());
+        assert!(!dir_path_buf.join(TEST_DIR_NAME).exists());
+        assert_eq!(
+            fs::read_to_string(dir_path_buf.join("README.md")).unwrap(),
+            "# chat"
+        );
+    }
+
+    #[test]
+    fn init_merge_test() {
+        let dir = gen_dir();
+        let dir_path_buf = &dir.path().to_path_buf();
+        fs::write(dir_path_buf.join("README.md"), "# My app").unwrap();
+        fs::write(dir_path_buf.join(GITIGNORE_FILE_NAME), "target/").unwrap();
+
+        assert!(init(dir_path_buf, None, &AppDetails::default(), false, true).is_err());
+
+        let report = init(dir_path_buf, None, &AppDetails::default(), true, true).unwrap();
+
+        assert!(dir_path_buf.join(APP_CONFIG_FILE_NAME).exists());
+        assert!(dir_path_buf.join(TEST_DIR_NAME).join("index.js").exists());
+        assert_eq!(
+            fs::read_to_string(dir_path_buf.join(GITIGNORE_FILE_NAME)).unwrap(),
+            "target/"
+        );
+        assert!(report.created.contains(&PathBuf::from(APP_CONFIG_FILE_NAME)));
+        assert_eq!(report.skipped, vec![PathBuf::from(GITIGNORE_FILE_NAME)]);
+
+        // running it again doesn't create anything
+        let report = init(dir_path_buf, None, &AppDetails::default(), true, true).unwrap();
+
+        assert!(report.created.is_empty());
+    }
+
+    #[test]
+    fn check_target_test() {
+        let dir = gen_dir();
+        let dir_path_buf = dir.path().to_path_buf();
+
+        assert!(check_target(&dir_path_buf.join("new_app"), false).is_ok());
+        assert!(check_target(&dir_path_buf, false).is_ok());
+
+        fs::write(dir_path_buf.join("README.md"), "# My app").unwrap();
+
+        let err = check_target(&dir_path_buf, false).unwrap_err();
+        assert!(err.to_string().contains("use --merge"));
+        assert!(check_target(&dir_path_buf, true).is_ok());
+    }
+
     #[test]
     fn setup_test_folder_test() {
         let dir = gen_dir();
         let dir_path_buf = &dir.path().to_path_buf();
-        setup_test_folder(dir_path_buf, &TEST_DIR_NAME).expect("Test folder not set up");
+        let mut report = InitReport::new(dir_path_buf);
+        let templates = Templates::load_from(dir_path_buf.join("no-overrides")).unwrap();
+        let data = AppDetails::default().template_data(dir_path_buf).unwrap();
+        setup_test_folder(&mut report, &templates, &data, dir_path_buf, &TEST_DIR_NAME).expect("Test folder not set up");
 
         assert!(dir_path_buf.join(&TEST_DIR_NAME).join("index.js").exists());
         assert!(dir_path_buf.join(&TEST_DIR_NAME).join("package.json").exists());
@@ -1,16 +1,22 @@
 mod agent;
+mod coverage;
+mod doctor;
 mod generate;
+mod generate_code;
 mod init;
 mod package;
+mod project_template;
 mod scaffold;
+mod templates;
 mod web;
 mod test;
-mod test_context;
 
 pub use self::agent::agent;
-pub use self::generate::generate;
-pub use self::init::init;
-pub use self::package::{package, unpack};
+pub use self::doctor::{doctor, Check};
+pub use self::generate::{generate, list_languages};
+pub use self::generate_code::{entry as generate_entry, function as generate_function};
+pub use self::init::{check_target as check_init_target, init, AppDetails, ZomeDetails, APP_CONFIG_FILE_NAME};
+pub use self::package::{package, unpack, BUILD_CONFIG_FILE_NAME, PROJECT_CONFIG_FILE_NAME};
 pub use self::web::web;
-pub use self::test::test;
+pub use self::test::{list as list_tests, test, TestSelection};
 pub use self::test::TEST_DIR_NAME;
@@ -1,17 +1,20 @@
 use base64;
-use cli::test_context::test_context;
 use colored::*;
-use config_files::Build;
-use error::DefaultResult;
-use holochain_core::nucleus::{ribosome, ZomeFnCall};
+use config_files::{Build, BuildContext, DEFAULT_PROFILE};
+use error::{DefaultResult, HolochainError};
+use failure::Error;
 use ignore::WalkBuilder;
+use sandbox::{self, Limits, Log};
 use serde_json::{self, Map, Value};
+use sha2::{Digest, Sha256};
 use std::{
+    collections::BTreeSet,
     fs::{self, File},
     io::{Read, Write},
     path::PathBuf,
 };
 use util;
+use wasm;
 
 pub const CODE_DIR_NAME: &str = "code";
 
@@ -21,10 +24,20 @@ pub const GITIGNORE_FILE_NAME: &str = ".gitignore";
 
 pub const IGNORE_FILE_NAME: &str = ".hcignore";
 
+pub const PROJECT_CONFIG_FILE_NAME: &str = ".hcconfig";
+
 pub const WASM_FILE_EXTENSION: &str = "wasm";
 
 pub const DEFAULT_BUNDLE_FILE_NAME: &str = "bundle.json";
 
+const NODE_MODULES_DIR_NAME: &str = "node_modules";
+
+const TARGET_DIR_NAME: &str = "target";
+
+/// Holds the output of the build steps of each zome, from the last run of `hc package`.
+/// Being hidden, it doesn't end up in the bundle.
+pub const BUILD_LOGS_DIR: &str = ".hc/logs";
+
 pub const META_FILE_ID: &str = "file";
 pub const META_DIR_ID: &str = "dir";
 pub const META_BIN_ID: &str = "bin";
@@ -35,34 +48,153 @@ pub const META_CONFIG_SECTION_NAME: &str = "config_file";
 
 pub type Object = Map<String, Value>;
 
+/// What `hc package` built, which is its result in `--output json`
+#[derive(Debug, Serialize)]
+pub struct PackageSummary {
+    pub bundle: PathBuf,
+    /// The SHA-256 of the bundle file, hex encoded
+    pub hash: String,
+    pub profile: String,
+    /// The zomes whose code was built, in the order they were bundled
+    pub zomes: Vec<String>,
+}
+
 struct Packager {
     strip_meta: bool,
+    limits: Limits,
+    verbose: bool,
+    zomes: Vec<String>,
+    project_root: PathBuf,
+    profile: String,
 }
 
 impl Packager {
-    fn new(strip_meta: bool) -> Packager {
-        Packager { strip_meta }
+    fn new(project_root: &PathBuf, strip_meta: bool, limits: Limits, verbose: bool, profile: &str) -> Packager {
+        Packager {
+            strip_meta,
+            limits,
+            verbose,
+            zomes: Vec::new(),
+            project_root: project_root.clone(),
+            profile: profile.to_string(),
+        }
     }
 
-    pub fn package(strip_meta: bool, output: Option<PathBuf>) -> DefaultResult<()> {
-        let output = output.unwrap_or_else(|| PathBuf::from(DEFAULT_BUNDLE_FILE_NAME));
+    pub fn package(
+        project_root: &PathBuf,
+        strip_meta: bool,
+        output: Option<PathBuf>,
+        limits: &Limits,
+        verbose: bool,
+        profile: &str,
+    ) -> DefaultResult<PackageSummary> {
+        // --output is the deprecated name of --bundle, easily taken for `hc --output json`
+        if let Some(ref output) = output {
+            if output.as_os_str() == "json" || output.as_os_str() == "human" {
+                log!(
+                    "{} writing the bundle to the file {:?}, for JSON output use `hc --output json package`",
+                    "Warning:".yellow().bold(),
+                    output
+                );
+            }
+        }
+
+        let output = output.unwrap_or_else(|| project_root.join(DEFAULT_BUNDLE_FILE_NAME));
 
-        Packager::new(strip_meta).run(&output)
+        Packager::new(project_root, strip_meta, limits.clone(), verbose, profile).run(output)
     }
 
-    fn run(&self, output: &PathBuf) -> DefaultResult<()> {
-        let dir_obj_bundle = self.bundle_recurse(&PathBuf::from("."))?;
+    fn run(mut self, output: PathBuf) -> DefaultResult<PackageSummary> {
+        self.project_root = self.project_root.canonicalize()?;
+
+        // the build steps append to the logs, which should only cover this run
+        let logs_path = self.project_root.join(BUILD_LOGS_DIR);
+        if logs_path.exists() {
+            fs::remove_dir_all(&logs_path)?;
+        }
+
+        self.check_profile()?;
 
-        let out_file = File::create(&output)?;
+        let project_root = self.project_root.clone();
+        let dir_obj_bundle = self.bundle_recurse(&project_root)?;
 
-        serde_json::to_writer_pretty(&out_file, &Value::from(dir_obj_bundle))?;
+        let bundle = serde_json::to_vec_pretty(&Value::from(dir_obj_bundle))?;
+        fs::write(&output, &bundle)?;
 
-        println!("{} bundle file at {:?}", "Created".green().bold(), output);
+        log!("{} bundle file at {:?}", "Created".green().bold(), output);
+
+        Ok(PackageSummary {
+            bundle: output,
+            hash: util::hex(&Sha256::digest(&bundle)),
+            profile: self.profile,
+            zomes: self.zomes,
+        })
+    }
+
+    /// Fails for a profile that no .build file defines, which is most likely a typo, and
+    /// warns about each zome that builds with its default steps instead
+    fn check_profile(&self) -> DefaultResult<()> {
+        if self.profile == DEFAULT_PROFILE {
+            return Ok(());
+        }
+
+        let mut defined = BTreeSet::new();
+        defined.insert(DEFAULT_PROFILE.to_string());
+        let mut without_profile = Vec::new();
+
+        for build_file in build_files(&self.project_root)? {
+            let build = Build::from_file(&build_file)?;
+            defined.extend(build.profiles.keys().cloned());
+
+            if !build.profiles.contains_key(&self.profile) {
+                // the .build is in the code folder of the zome
+                let zome_name = build_file
+                    .parent()
+                    .and_then(|code_path| code_path.parent())
+                    .and_then(|zome_path| zome_path.file_name())
+                    .map(|zome_name| zome_name.to_string_lossy().into_owned())
+                    .unwrap_or_default();
+                without_profile.push(zome_name);
+            }
+        }
+
+        if !defined.contains(&self.profile) {
+            return Err(HolochainError::config(
+                &self.project_root,
+                format!(
+                    "unknown profile {:?}, the .build files of the app define {}",
+                    self.profile,
+                    defined.into_iter().collect::<Vec<_>>().join(", ")
+                ),
+            ).into());
+        }
+
+        for zome_name in without_profile {
+            log!(
+                "{} zome {:?} doesn't define the profile {:?}, so it is built with its default steps",
+                "Warning:".yellow().bold(),
+                zome_name,
+                self.profile
+            );
+        }
 
         Ok(())
     }
 
-    fn bundle_recurse(&self, path: &PathBuf) -> DefaultResult<Object> {
+    fn build_context(&self, zome_name: &str) -> BuildContext {
+        BuildContext {
+            zome_name: zome_name.to_string(),
+            project_root: self.project_root.clone(),
+            profile: self.profile.clone(),
+            // the output of the build steps of each zome goes to a file of its own
+            log_file: self
+                .project_root
+                .join(BUILD_LOGS_DIR)
+                .join(format!("{}.log", zome_name)),
+        }
+    }
+
+    fn bundle_recurse(&mut self, path: &PathBuf) -> DefaultResult<Object> {
         let root_dir = WalkBuilder::new(path)
             .max_depth(Some(1))
             .add_custom_ignore_filename(IGNORE_FILE_NAME)
@@ -132,30 +264,49 @@ impl Packager {
                 {
                     meta_tree.insert(file_name.clone(), META_DIR_ID.into());
 
-                    let build = Build::from_file(dir_with_code.join(BUILD_CONFIG_FILE_NAME))?;
+                    let build = Build::from_file(dir_with_code.join(BUILD_CONFIG_FILE_NAME))?
+                        .for_profile(&self.profile);
 
-                    let wasm = build.run(&dir_with_code)?;
+                    let wasm = build.run(&dir_with_code, &self.build_context(&file_name))?;
 
                     let wasm_binary = base64::decode(&wasm)?;
 
-                    // Instantiating WASM and calling function to get JSON:
-                    // ribosome::run_dna is the WASM run-time imported from Holochain core.
-                    // We need to setup a shallow test context which actually is not needed
-                    // here so it doesn't matter what is in there.
-                    // TODO: extract core of run_dna() into a function that does not need a context
-                    let context = test_context("HC");
+                    // catch a wrong artifact here, instead of with an obscure error from the ribosome
+                    wasm::validate_zome(&wasm_binary).map_err(|err| {
+                        HolochainError::Wasm(format!(
+                            "artifact {:?} of zome {:?}: {}",
+                            build.artifact,
+                            file_name,
+                            err
+                        ))
+                    })?;
+
                     // We just call into __hdk_get_json_definition() without any arguments.
                     // What we get back is a JSON string with all the entry types and zome functions
                     // defined in that WASM code, constructed through our Rust macros define_zome!
-                    // and entry!.
-                    let call_result = ribosome::run_dna(
-                        "HC",
-                        context,
-                        wasm_binary,
-                        &ZomeFnCall::new("", "", "__hdk_get_json_definition", ""),
-                        Some("{}".as_bytes().to_vec()),
-                    )?;
-                    let json_from_wasm: Map<String, Value> = serde_json::from_str(&call_result.to_string())?;
+                    // and entry!. The zome runs in a sandbox, without any Holochain context.
+                    let log = Log::default();
+                    let json_from_wasm = sandbox::extract_definition(wasm_binary, &self.limits, &log)
+                        .map_err(|err| {
+                            let messages = log.messages();
+
+                            if messages.is_empty() {
+                                HolochainError::Wasm(format!("zome {:?}: {}", file_name, err))
+                            } else {
+                                HolochainError::Wasm(format!(
+                                    "zome {:?}: {}\n{}",
+                                    file_name,
+                                    err,
+                                    format_log(&file_name, &messages)
+                                ))
+                            }
+                        })?;
+
+                    if self.verbose {
+                        log!("{}", format_log(&file_name, &log.messages()));
+                    }
+
+                    self.zomes.push(file_name.clone());
 
                     let mut sub_tree_content = self.bundle_recurse(&node)?;
                     for key in json_from_wasm.keys() {
@@ -174,9 +325,16 @@ impl Packager {
                 {
                     meta_tree.insert(file_name.clone(), META_BIN_ID.into());
 
-                    let build = Build::from_file(build_config)?;
+                    let build = Build::from_file(build_config)?.for_profile(&self.profile);
+
+                    // this folder is inside the zome's folder
+                    let zome_name = node
+                        .parent()
+                        .and_then(|zome_path| zome_path.file_name())
+                        .map(|zome_name| zome_name.to_string_lossy().into_owned())
+                        .unwrap_or_else(|| file_name.clone());
 
-                    let wasm = build.run(&node)?;
+                    let wasm = build.run(&node, &self.build_context(&zome_name))?;
 
                     // here insert the wasm itself
                     main_tree.insert(file_name.clone(), json!({ "code": wasm }));
@@ -204,11 +362,61 @@ impl Packager {
     }
 }
 
-pub fn package(strip_meta: bool, output: Option<PathBuf>) -> DefaultResult<()> {
-    Packager::package(strip_meta, output)
+/// The debug messages of a zome, one per line below a heading
+fn format_log(zome: &str, messages: &[String]) -> String {
+    let mut log = format!("{} of zome {:?}:", "Log".green().bold(), zome);
+
+    if messages.is_empty() {
+        log.push_str("\n    (no messages)");
+    }
+
+    for message in messages {
+        log.push_str("\n    ");
+        log.push_str(message);
+    }
+
+    log
 }
 
-pub fn unpack(path: &PathBuf, to: &PathBuf) -> DefaultResult<()> {
+/// Finds the .build files of the project, skipping the build output of its zomes
+pub fn build_files(path: &PathBuf) -> DefaultResult<Vec<PathBuf>> {
+    let mut files = Vec::new();
+
+    let walker = WalkBuilder::new(path)
+        .hidden(false)
+        .filter_entry(|entry| {
+            let name = entry.file_name();
+            name != ".git" && name != NODE_MODULES_DIR_NAME && name != TARGET_DIR_NAME
+        })
+        .build();
+
+    for entry in walker {
+        let entry = entry?;
+
+        if entry.file_name() == BUILD_CONFIG_FILE_NAME && entry.path().is_file() {
+            files.push(entry.path().to_path_buf());
+        }
+    }
+
+    files.sort();
+    Ok(files)
+}
+
+/// Builds the project at the given root into a bundle, which is written to the root unless
+/// an output file is given
+pub fn package(
+    project_root: &PathBuf,
+    strip_meta: bool,
+    output: Option<PathBuf>,
+    limits: &Limits,
+    verbose: bool,
+    profile: &str,
+) -> DefaultResult<PackageSummary> {
+    Packager::package(project_root, strip_meta, output, limits, verbose, profile)
+}
+
+/// Restores the files of a bundle and returns their paths, as well as the folders created
+pub fn unpack(path: &PathBuf, to: &PathBuf) -> DefaultResult<Vec<PathBuf>> {
     ensure!(path.is_file(), "argument \"path\" doesn't point to a file");
 
     if !to.exists() {
@@ -218,67 +426,81 @@ pub fn unpack(path: &PathBuf, to: &PathBuf) -> DefaultResult<()> {
     ensure!(to.is_dir(), "argument \"to\" doesn't point to a directory");
 
     let raw_bundle_content = fs::read_to_string(&path)?;
-    let bundle_content: Object = serde_json::from_str(&raw_bundle_content)?;
+    let bundle_content: Object = serde_json::from_str(&raw_bundle_content)
+        .map_err(|err| HolochainError::Bundle(format!("{:?} isn't a JSON object: {}", path, err)))?;
 
-    unpack_recurse(bundle_content, &to)?;
+    let mut created = Vec::new();
+    unpack_recurse(bundle_content, &to, &mut created)?;
 
-    Ok(())
+    Ok(created)
 }
 
-fn unpack_recurse(mut obj: Object, to: &PathBuf) -> DefaultResult<()> {
+fn incompatible_meta_section() -> Error {
+    HolochainError::Bundle("incompatible meta section".into()).into()
+}
+
+fn unpack_recurse(mut obj: Object, to: &PathBuf, created: &mut Vec<PathBuf>) -> DefaultResult<()> {
     if let Some(Value::Object(mut main_meta_obj)) = obj.remove(META_SECTION_NAME) {
         // unpack the tree
         if let Some(Value::Object(tree_meta_obj)) = main_meta_obj.remove(META_TREE_SECTION_NAME) {
             for (meta_entry, meta_value) in tree_meta_obj {
                 let entry = obj
                     .remove(&meta_entry)
-                    .ok_or_else(|| format_err!("incompatible meta section"))?;
+                    .ok_or_else(incompatible_meta_section)?;
 
                 if let Value::String(node_type) = meta_value {
                     match node_type.as_str() {
                         META_FILE_ID if entry.is_string() => {
                             let base64_content = entry.as_str().unwrap().to_string();
-                            let content = base64::decode(&base64_content)?;
+                            let content = base64::decode(&base64_content).map_err(|err| {
+                                HolochainError::Bundle(format!("{:?} isn't Base64 encoded: {}", meta_entry, err))
+                            })?;
 
-                            let mut file_path = to.join(meta_entry);
+                            let file_path = to.join(meta_entry);
 
-                            File::create(file_path)?.write_all(&content[..])?;
+                            File::create(&file_path)?.write_all(&content[..])?;
+                            created.push(file_path);
                         }
                         META_BIN_ID if entry.is_object() => {
                             let base64_content = entry[&meta_entry].to_string();
-                            let content = base64::decode(&base64_content)?;
+                            let content = base64::decode(&base64_content).map_err(|err| {
+                                HolochainError::Bundle(format!("{:?} isn't Base64 encoded: {}", meta_entry, err))
+                            })?;
 
-                            let mut file_path =
+                            let file_path =
                                 to.join(meta_entry).with_extension(WASM_FILE_EXTENSION);
 
-                            File::create(file_path)?.write_all(&content[..])?;
+                            File::create(&file_path)?.write_all(&content[..])?;
+                            created.push(file_path);
                         }
                         META_DIR_ID if entry.is_object() => {
                             let directory_obj = entry.as_object().unwrap();
                             let dir_path = to.join(meta_entry);
 
                             fs::create_dir(&dir_path)?;
+                            created.push(dir_path.clone());
 
-                            unpack_recurse(directory_obj.clone(), &dir_path)?;
+                            unpack_recurse(directory_obj.clone(), &dir_path, created)?;
                         }
-                        _ => bail!("incompatible meta section"),
+                        _ => return Err(incompatible_meta_section()),
                     }
                 } else {
-                    bail!("incompatible meta section");
+                    return Err(incompatible_meta_section());
                 }
             }
         }
 
         // unpack the config file
         if let Some(config_file_meta) = main_meta_obj.remove(META_CONFIG_SECTION_NAME) {
-            ensure!(
-                config_file_meta.is_string(),
-                "config file has to be a string"
-            );
+            if !config_file_meta.is_string() {
+                return Err(HolochainError::Bundle("config file has to be a string".into()).into());
+            }
 
             if !obj.is_empty() {
-                let dna_file = File::create(to.join(config_file_meta.as_str().unwrap()))?;
+                let dna_file_path = to.join(config_file_meta.as_str().unwrap());
+                let dna_file = File::create(&dna_file_path)?;
                 serde_json::to_writer_pretty(dna_file, &obj)?;
+                created.push(dna_file_path);
             }
         }
     }
@@ -291,16 +513,7 @@ mod tests {
     use super::*;
     use assert_cmd::prelude::*;
     use std::process::Command;
-    use tempfile::{Builder, TempDir};
-
-    const HOLOCHAIN_TEST_PREFIX: &str = "org.holochain.test";
-
-    fn gen_dir() -> TempDir {
-        Builder::new()
-            .prefix(HOLOCHAIN_TEST_PREFIX)
-            .tempdir()
-            .unwrap()
-    }
+    use test_utils::gen_dir;
 
     #[test]
     fn package_and_unpack_isolated() {
@@ -436,4 +649,332 @@ mod tests {
             .assert()
             .success();
     }
+
+    /// Whether make and clang are there to build C zomes, with the wasm32 target and wasm-ld
+    fn builds_c_zomes() -> bool {
+        let tmp = gen_dir();
+        let source_path = tmp.path().join("empty.c");
+        fs::write(&source_path, "").unwrap();
+
+        let succeeds = |command: &mut Command| {
+            command
+                .output()
+                .map(|output| output.status.success())
+                .unwrap_or(false)
+        };
+
+        succeeds(Command::new("make").arg("--version"))
+            && succeeds(
+                Command::new("clang")
+                    .args(&["--target=wasm32-unknown-unknown", "-nostdlib", "-Wl,--no-entry", "-o"])
+                    .arg(tmp.path().join("empty.wasm"))
+                    .arg(&source_path),
+            )
+    }
+
+    #[test]
+    fn c_zome_compilation() {
+        // skip where clang can't build WASM, like without the wasm32 target or wasm-ld
+        if !builds_c_zomes() {
+            return;
+        }
+
+        let tmp = gen_dir();
+
+        Command::main_binary()
+            .unwrap()
+            .current_dir(&tmp.path())
+            .args(&["init", "."])
+            .assert()
+            .success();
+
+        Command::main_binary()
+            .unwrap()
+            .current_dir(&tmp.path())
+            .args(&["g", "zomes/greeter", "c"])
+            .assert()
+            .success();
+
+        Command::main_binary()
+            .unwrap()
+            .current_dir(&tmp.path())
+            .args(&["package"])
+            .assert()
+            .success();
+
+        let bundle: Object = serde_json::from_str(
+            &fs::read_to_string(tmp.path().join(DEFAULT_BUNDLE_FILE_NAME)).unwrap(),
+        ).unwrap();
+
+        assert!(bundle["zomes"]["greeter"]["code"]["code"].is_string());
+        assert!(bundle["zomes"]["greeter"]["capabilities"]["main"].is_object());
+    }
+
+    #[test]
+    fn rejects_invalid_artifacts() {
+        let tmp = gen_dir();
+        let code_path = tmp.path().join("zomes/blog/code");
+
+        fs::create_dir_all(&code_path).unwrap();
+        fs::write(tmp.path().join("app.json"), "{}").unwrap();
+        fs::write(code_path.join("notes.txt"), "not wasm").unwrap();
+        Build::with_artifact("notes.txt")
+            .save_as(code_path.join(BUILD_CONFIG_FILE_NAME))
+            .unwrap();
+
+        let output = Command::main_binary()
+            .unwrap()
+            .current_dir(&tmp.path())
+            .args(&["package"])
+            .output()
+            .unwrap();
+
+        assert_eq!(output.status.code(), Some(5));
+        assert!(String::from_utf8_lossy(&output.stderr)
+            .contains("artifact \"notes.txt\" of zome \"blog\": not a valid WASM module"));
+    }
+
+    #[test]
+    fn json_output() {
+        let tmp = gen_dir();
+
+        let output = Command::main_binary()
+            .unwrap()
+            .current_dir(&tmp.path())
+            .args(&["--output", "json", "init", "."])
+            .output()
+            .unwrap();
+
+        assert!(output.status.success());
+
+        // the human messages go to stderr, leaving a single JSON object on stdout
+        let result: Value = serde_json::from_slice(&output.stdout).unwrap();
+        assert_eq!(result["success"], true);
+        assert!(result["result"]["created"]
+            .as_array()
+            .unwrap()
+            .contains(&json!("app.json")));
+
+        let code_path = tmp.path().join("zomes/blog/code");
+        fs::create_dir_all(&code_path).unwrap();
+        fs::write(code_path.join("notes.txt"), "not wasm").unwrap();
+        Build::with_artifact("notes.txt")
+            .save_as(code_path.join(BUILD_CONFIG_FILE_NAME))
+            .unwrap();
+
+        let output = Command::main_binary()
+            .unwrap()
+            .current_dir(&tmp.path())
+            .args(&["--output", "json", "package"])
+            .output()
+            .unwrap();
+
+        let result: Value = serde_json::from_slice(&output.stdout).unwrap();
+        assert_eq!(result["success"], false);
+        assert_eq!(result["error"]["code"], 5);
+        assert_eq!(result["error"]["kind"], "wasm");
+    }
+
+    #[test]
+    fn output_after_package_is_the_bundle_file() {
+        let tmp = gen_dir();
+
+        Command::main_binary()
+            .unwrap()
+            .current_dir(&tmp.path())
+            .args(&["init", "."])
+            .assert()
+            .success();
+
+        // the deprecated name of --bundle keeps working
+        Command::main_binary()
+            .unwrap()
+            .current_dir(&tmp.path())
+            .args(&["package", "--output", "out.json"])
+            .assert()
+            .success();
+
+        assert!(tmp.path().join("out.json").exists());
+
+        // but not silently where it looks like the output format
+        let output = Command::main_binary()
+            .unwrap()
+            .current_dir(&tmp.path())
+            .args(&["package", "--output", "json"])
+            .output()
+            .unwrap();
+
+        assert!(output.status.success());
+        assert!(String::from_utf8_lossy(&output.stdout)
+            .contains("Warning: writing the bundle to the file \"json\", for JSON output use `hc --output json package`"));
+    }
+
+    #[test]
+    fn packages_the_project_from_subfolders() {
+        let tmp = gen_dir();
+        let docs_path = tmp.path().join("docs");
+
+        fs::create_dir_all(&docs_path).unwrap();
+        fs::write(tmp.path().join("app.json"), r#"{ "name": "blog" }"#).unwrap();
+        fs::write(docs_path.join("docs.json"), r#"{ "title": "Blog" }"#).unwrap();
+
+        Command::main_binary()
+            .unwrap()
+            .current_dir(&docs_path)
+            .args(&["package"])
+            .assert()
+            .success();
+
+        let bundle: Value = serde_json::from_str(
+            &fs::read_to_string(tmp.path().join(DEFAULT_BUNDLE_FILE_NAME)).unwrap(),
+        ).unwrap();
+        assert_eq!(bundle["name"], "blog");
+        assert_eq!(bundle["docs"]["title"], "Blog");
+
+        // the project can also be chosen from outside of it
+        let outside = gen_dir();
+        let bundle_file_path = outside.path().join("outside.json");
+
+        Command::main_binary()
+            .unwrap()
+            .current_dir(&outside.path())
+            .args(&["--project-dir", tmp.path().to_str().unwrap()])
+            .args(&["package", "-o", "outside.json"])
+            .assert()
+            .success();
+
+        assert!(bundle_file_path.exists());
+
+        // without an app.json above it, there is no project to package
+        Command::main_binary()
+            .unwrap()
+            .current_dir(&outside.path())
+            .args(&["package"])
+            .assert()
+            .code(3);
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn expands_variables_in_build_steps() {
+        let tmp = gen_dir();
+        let code_path = tmp.path().join("code");
+        fs::create_dir_all(&code_path).unwrap();
+
+        let build: Build = serde_json::from_value(json!({
+            "steps": {
+                "sh": {
+                    "args": ["-c", "printf \"$GREETING\" > ${ZOME_NAME}-${PROFILE}.out"],
+                    "env": { "GREETING": "hello from ${ZOME_NAME}" }
+                }
+            },
+            "artifact": "${ZOME_NAME}-${PROFILE}.out"
+        })).unwrap();
+        let context = BuildContext {
+            zome_name: "blog".into(),
+            project_root: tmp.path().to_path_buf(),
+            profile: "debug".into(),
+            log_file: tmp.path().join("blog.log"),
+        };
+
+        let artifact = build.run(&code_path, &context).unwrap();
+
+        assert_eq!(base64::decode(&artifact).unwrap(), b"hello from blog");
+
+        let build = Build::with_artifact("${NO_SUCH_VARIABLE_IN_HC_TESTS}.wasm");
+        let err = build.run(&code_path, &context).unwrap_err();
+
+        assert!(err.to_string().contains("unknown variable ${NO_SUCH_VARIABLE_IN_HC_TESTS}"));
+
+        // a doubled dollar sign escapes a variable
+        assert_eq!(
+            context.expand("echo $${HOME} in ${ZOME_NAME}, $$${ZOME_NAME}").unwrap(),
+            "echo ${HOME} in blog, $${ZOME_NAME}"
+        );
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn checks_the_profile() {
+        let tmp = gen_dir();
+        fs::write(tmp.path().join("app.json"), "{}").unwrap();
+
+        let failing = Build::with_artifact("zome.wasm").cmd("sh", &["-c", "exit 3"]);
+        let blog_path = tmp.path().join("zomes/blog/code");
+        let chat_path = tmp.path().join("zomes/chat/code");
+        fs::create_dir_all(&blog_path).unwrap();
+        fs::create_dir_all(&chat_path).unwrap();
+        failing
+            .clone()
+            .profile("debug", failing.clone())
+            .save_as(blog_path.join(BUILD_CONFIG_FILE_NAME))
+            .unwrap();
+        failing.save_as(chat_path.join(BUILD_CONFIG_FILE_NAME)).unwrap();
+
+        let output = Command::main_binary()
+            .unwrap()
+            .current_dir(&tmp.path())
+            .args(&["package", "--profile", "debg"])
+            .output()
+            .unwrap();
+
+        assert_eq!(output.status.code(), Some(3));
+        assert!(String::from_utf8_lossy(&output.stderr)
+            .contains("unknown profile \"debg\", the .build files of the app define debug, release"));
+
+        // a profile some zomes define is fine, with a warning for the others
+        let output = Command::main_binary()
+            .unwrap()
+            .current_dir(&tmp.path())
+            .args(&["package", "--profile", "debug"])
+            .output()
+            .unwrap();
+
+        assert_eq!(output.status.code(), Some(4));
+
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        assert!(stdout.contains("zome \"chat\" doesn't define the profile \"debug\""));
+        assert!(!stdout.contains("zome \"blog\" doesn't define"));
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn reports_failed_build_steps() {
+        let tmp = gen_dir();
+        let code_path = tmp.path().join("zomes/blog/code");
+
+        fs::create_dir_all(&code_path).unwrap();
+        fs::write(tmp.path().join("app.json"), "{}").unwrap();
+        Build::with_artifact("blog.wasm")
+            .cmd("sh", &["-c", "echo building; echo broken >&2; exit 3"])
+            .save_as(code_path.join(BUILD_CONFIG_FILE_NAME))
+            .unwrap();
+
+        let output = Command::main_binary()
+            .unwrap()
+            .current_dir(&tmp.path())
+            .args(&["package"])
+            .output()
+            .unwrap();
+
+        assert_eq!(output.status.code(), Some(4));
+
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        let stderr = String::from_utf8_lossy(&output.stderr);
+
+        // shown live, prefixed with the zome
+        let prefixed = |output: &str, line: &str| {
+            output.lines().any(|output_line| output_line.contains("blog |") && output_line.ends_with(line))
+        };
+        assert!(prefixed(&stdout, "building"));
+        assert!(prefixed(&stderr, "broken"));
+
+        assert!(stderr.contains("Build error: zome \"blog\": command `sh -c echo building; echo broken >&2; exit 3` exited with code 3 after"));
+        assert!(stderr.contains("last lines of stderr:\n    broken\n"));
+
+        let log = fs::read_to_string(tmp.path().join(BUILD_LOGS_DIR).join("blog.log")).unwrap();
+        // both streams end up in the log, in whichever order their lines were read
+        assert!(log.lines().any(|line| line == "building"));
+        assert!(log.lines().any(|line| line == "broken"));
+    }
 }
@@ -0,0 +1,197 @@
+use cli::init::InitReport;
+use colored::*;
+use error::DefaultResult;
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+use util;
+
+/// The folder, inside the hc config folder, holding named project templates
+pub const PROJECT_TEMPLATES_DIR_NAME: &str = "project-templates";
+
+/// The folder, inside the project templates folder, where templates cloned from git are cached
+pub const TEMPLATE_CACHE_DIR_NAME: &str = ".cache";
+
+const GIT_DIR_NAME: &str = ".git";
+
+/// The values substituted for the `<<APP_NAME>>`, `<<AUTHOR>>` and `<<DESCRIPTION>>`
+/// placeholders in the names and contents of template files
+#[derive(Clone, Debug)]
+pub struct TemplateVariables {
+    pub name: String,
+    pub author: String,
+    pub description: String,
+}
+
+impl TemplateVariables {
+    pub fn apply(&self, text: &str) -> String {
+        text.replace("<<APP_NAME>>", &self.name)
+            .replace("<<AUTHOR>>", &self.author)
+            .replace("<<DESCRIPTION>>", &self.description)
+    }
+}
+
+fn is_git_url(template: &str) -> bool {
+    template.contains("://") || template.starts_with("git@") || template.ends_with(".git")
+}
+
+/// Turns a git URL into a folder name for the template cache
+fn cache_dir_name(url: &str) -> String {
+    url.trim_end_matches('/')
+        .trim_end_matches(".git")
+        .chars()
+        .map(|c| if c.is_alphanumeric() || c == '-' || c == '_' { c } else { '_' })
+        .collect()
+}
+
+/// Finds the folder of the given template, which is either a path to a local folder, a git
+/// URL which gets cloned into the template cache on first use, or the name of a folder
+/// inside `~/.config/hc/project-templates`
+pub fn resolve_template(template: &str) -> DefaultResult<PathBuf> {
+    let local_path = PathBuf::from(template);
+
+    if local_path.is_dir() {
+        return Ok(local_path);
+    }
+
+    let templates_path = util::config_dir()?.join(PROJECT_TEMPLATES_DIR_NAME);
+
+    if is_git_url(template) {
+        let cache_path = templates_path.join(TEMPLATE_CACHE_DIR_NAME);
+        let dir_name = cache_dir_name(template);
+
+        if !cache_path.join(&dir_name).exists() {
+            fs::create_dir_all(&cache_path)?;
+
+            log!("{} template from {}", "Cloning".green().bold(), template);
+
+            util::run_cmd(
+                cache_path.clone(),
+                "git".into(),
+                vec![
+                    "clone".to_owned(),
+                    "--depth".to_owned(),
+                    "1".to_owned(),
+                    template.to_owned(),
+                    dir_name.clone(),
+                ],
+            )?;
+        }
+
+        return Ok(cache_path.join(dir_name));
+    }
+
+    let named_path = templates_path.join(template);
+
+    ensure!(
+        named_path.is_dir(),
+        "template {:?} is neither a folder, a git URL nor a template in {:?}",
+        template,
+        templates_path
+    );
+
+    Ok(named_path)
+}
+
+/// Copies the template folder to the destination, substituting the template variables in
+/// file names and in the contents of all text files. Git metadata is left out, as well as
+/// files that already exist at the destination.
+pub fn instantiate<P: AsRef<Path>, Q: AsRef<Path>>(
+    report: &mut InitReport,
+    template_path: P,
+    dest_path: Q,
+    variables: &TemplateVariables,
+) -> DefaultResult<()> {
+    for entry in fs::read_dir(&template_path)? {
+        let source = entry?.path();
+        let file_name = util::file_name_string(&source)?;
+
+        if file_name == GIT_DIR_NAME {
+            continue;
+        }
+
+        let dest = dest_path.as_ref().join(variables.apply(&file_name));
+
+        if source.is_dir() {
+            report.create_dir(&dest)?;
+            instantiate(report, &source, &dest, variables)?;
+        } else {
+            let content = fs::read(&source)?;
+
+            match String::from_utf8(content) {
+                Ok(text) => report.write_file(&dest, variables.apply(&text))?,
+                // binary files get copied as they are
+                Err(err) => report.write_file(&dest, err.into_bytes())?,
+            }
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_utils::gen_dir;
+
+    fn variables() -> TemplateVariables {
+        TemplateVariables {
+            name: "chat".into(),
+            author: "Jane <jane@example.com>".into(),
+            description: "A chat app".into(),
+        }
+    }
+
+    #[test]
+    fn instantiate_test() {
+        let template = gen_dir();
+        let template_path = template.path().to_path_buf();
+
+        fs::create_dir_all(template_path.join(".git")).unwrap();
+        fs::write(template_path.join(".git/HEAD"), "ref: refs/heads/master").unwrap();
+        fs::create_dir_all(template_path.join("zomes/<<APP_NAME>>")).unwrap();
+        fs::write(
+            template_path.join("zomes/<<APP_NAME>>/README.md"),
+            "# <<APP_NAME>>\n<<DESCRIPTION>> by <<AUTHOR>>",
+        ).unwrap();
+        fs::write(template_path.join("logo.bin"), &[0xff, 0xfe, 0x00]).unwrap();
+
+        let dest = gen_dir();
+        let dest_path = dest.path().join("app");
+        let mut report = InitReport::new(&dest_path);
+
+        instantiate(&mut report, &template_path, &dest_path, &variables()).unwrap();
+
+        assert!(!dest_path.join(".git").exists());
+        assert_eq!(
+            fs::read_to_string(dest_path.join("zomes/chat/README.md")).unwrap(),
+            "# chat\nA chat app by Jane <jane@example.com>"
+        );
+        assert_eq!(fs::read(dest_path.join("logo.bin")).unwrap(), vec![0xff, 0xfe, 0x00]);
+        assert!(report.created.contains(&PathBuf::from("zomes/chat/README.md")));
+    }
+
+    #[test]
+    fn resolves_local_templates() {
+        let template = gen_dir();
+        let template_path = template.path().to_str().unwrap();
+
+        assert_eq!(
+            resolve_template(template_path).unwrap(),
+            PathBuf::from(template_path)
+        );
+    }
+
+    #[test]
+    fn cache_dir_name_test() {
+        assert!(is_git_url("https://github.com/holochain/app-template.git"));
+        assert!(is_git_url("git@github.com:holochain/app-template"));
+        assert!(!is_git_url("my-template"));
+
+        assert_eq!(
+            cache_dir_name("https://github.com/holochain/app-template.git"),
+            "https___github_com_holochain_app-template"
+        );
+    }
+}
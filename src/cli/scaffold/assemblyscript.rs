@@ -1,9 +1,8 @@
-use cli::{package, scaffold::Scaffold};
+use cli::{package, scaffold::Scaffold, templates::Templates};
 use config_files::Build;
 use error::DefaultResult;
 use std::{
-    fs::{self, OpenOptions},
-    io::Write,
+    fs,
     path::Path,
 };
 use util;
@@ -11,18 +10,25 @@ use util;
 pub const TSCONFIG_FILE_NAME: &str = "tsconfig.json";
 pub const TYPESCRIPT_FILE_NAME: &str = "index.ts";
 
+pub const PACKAGE_JSON_FILE_NAME: &str = "package.json";
+
 pub struct AssemblyScriptScaffold {
     build_template: Build,
+    package_name: String,
 }
 
 impl AssemblyScriptScaffold {
-    pub fn new() -> AssemblyScriptScaffold {
+    pub fn new(package_name: String) -> AssemblyScriptScaffold {
+        let artifact_name = format!("{}.wasm", package_name);
         AssemblyScriptScaffold {
-            build_template: Build::with_artifact("module.wasm")
+            // the compiler and hdk-assemblyscript get installed before the first build
+            build_template: Build::with_artifact(artifact_name.clone())
+                .cmd("npm".to_owned(), &["install".to_owned()])
                 .cmd(
-                    "./node_modules/assemblyscript/bin/asc",
-                    &["index.ts", "-b", "module.wasm", "--transform", "./node_modules/hdk-assemblyscript/transforms"],
+                    "./node_modules/assemblyscript/bin/asc".to_owned(),
+                    &["index.ts".to_owned(), "-b".to_owned(), artifact_name, "--transform".to_owned(), "./node_modules/hdk-assemblyscript/transforms".to_owned()],
                 ),
+            package_name: package_name,
         }
     }
 }
@@ -31,50 +37,79 @@ impl Scaffold for AssemblyScriptScaffold {
     fn gen<P: AsRef<Path>>(&self, base_path: P) -> DefaultResult<()> {
         fs::create_dir_all(&base_path)?;
 
-        // use npm to initialise a nodejs project
-        util::run_cmd(
-            base_path.as_ref().to_path_buf(),
-            "npm".into(),
-            vec![
-                "init".to_owned(),
-                "-y".to_owned(),
-            ],
-        )?;
+        // render the package files directly, so that no network access is needed
+        let templates = Templates::load()?;
+        let data = json!({ "name": self.package_name });
 
-        // add hdk-assemblyscript as a dependency
-        util::run_cmd(
-            base_path.as_ref().to_path_buf(),
-            "npm".into(),
-            vec![
-                "install".to_owned(),
-                "--save".to_owned(),
-                "holochain/hdk-assemblyscript".to_owned(),
-            ],
-        )?;
-
-        // create a index.ts file
-        let typescript_file_path = base_path.as_ref().join(TYPESCRIPT_FILE_NAME);
-
-        let mut typescript_file = OpenOptions::new().write(true).create(true).open(typescript_file_path)?;
+        for (file_name, template) in &[
+            (PACKAGE_JSON_FILE_NAME, "assemblyscript/package.json"),
+            (TYPESCRIPT_FILE_NAME, "assemblyscript/index.ts"),
+            (TSCONFIG_FILE_NAME, "assemblyscript/tsconfig.json"),
+        ] {
+            fs::write(base_path.as_ref().join(file_name), templates.render(template, &data)?)?;
+        }
 
-        let js_starter = include_str!("assemblyscript/index-ts-starter.ts");
+        // create and fill in a build file appropriate for AssemblyScript
+        let build_file_path = base_path.as_ref().join(package::BUILD_CONFIG_FILE_NAME);
 
-        typescript_file.write_all(js_starter.as_bytes())?;
+        self.build_template.save_as(build_file_path)?;
 
-        // create a tsconfig.json file
-        let tsconfig_file_path = base_path.as_ref().join(TSCONFIG_FILE_NAME);
+        Ok(())
+    }
 
-        let mut tsconfig_file = OpenOptions::new().write(true).create(true).open(tsconfig_file_path)?;
+    fn install<P: AsRef<Path>>(&self, base_path: P) -> DefaultResult<()> {
+        // fetch hdk-assemblyscript and the AssemblyScript compiler
+        util::run_cmd(
+            base_path.as_ref().to_path_buf(),
+            "npm".into(),
+            vec!["install".to_owned()],
+        )
+    }
+}
 
-        let tsconfig_json = include_str!("assemblyscript/tsconfig.json");
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::{self, Value};
+    use test_utils::gen_dir;
+
+    #[test]
+    fn gen_writes_package_offline() {
+        let dir = gen_dir();
+        let code_path = dir.path().join("code");
+
+        AssemblyScriptScaffold::new("blog".into())
+            .gen(&code_path)
+            .unwrap();
+
+        let package_json: Value = serde_json::from_str(
+            &fs::read_to_string(code_path.join(PACKAGE_JSON_FILE_NAME)).unwrap(),
+        ).unwrap();
+
+        assert_eq!(package_json["name"], "blog");
+        assert!(package_json["dependencies"]["hdk-assemblyscript"].is_string());
+        assert!(code_path.join(TSCONFIG_FILE_NAME).exists());
+        assert!(code_path.join(TYPESCRIPT_FILE_NAME).exists());
+        assert!(!code_path.join("node_modules").exists());
+    }
 
-        tsconfig_file.write_all(tsconfig_json.as_bytes())?;
+    #[test]
+    fn build_installs_the_packages_before_compiling() {
+        let dir = gen_dir();
+        let code_path = dir.path().join("code");
 
-        // create and fill in a build file appropriate for AssemblyScript
-        let build_file_path = base_path.as_ref().join(package::BUILD_CONFIG_FILE_NAME);
+        AssemblyScriptScaffold::new("blog".into())
+            .gen(&code_path)
+            .unwrap();
 
-        self.build_template.save_as(build_file_path)?;
+        let build = Build::from_file(code_path.join(package::BUILD_CONFIG_FILE_NAME)).unwrap();
+        let steps: Vec<_> = build
+            .steps
+            .into_iter()
+            .map(|(bin, step)| (bin.as_str(), step.args().to_vec()))
+            .collect();
 
-        Ok(())
+        assert_eq!(steps[0], ("npm", vec!["install".to_string()]));
+        assert_eq!(steps[1].0, "./node_modules/assemblyscript/bin/asc");
     }
 }
@@ -0,0 +1,80 @@
+use cli::{package, scaffold::Scaffold, templates::Templates};
+use config_files::Build;
+use error::DefaultResult;
+use std::{
+    fs,
+    path::Path,
+};
+
+pub const MAKEFILE_FILE_NAME: &str = "Makefile";
+pub const ZOME_C_PATH: &str = "src/zome.c";
+pub const HDK_H_PATH: &str = "src/hdk.h";
+
+pub struct CScaffold {
+    build_template: Build,
+    package_name: String,
+}
+
+impl CScaffold {
+    pub fn new(package_name: String) -> CScaffold {
+        let artifact_name = format!("{}.wasm", package_name);
+        CScaffold {
+            build_template: Build::with_artifact(artifact_name).cmd("make", &[]),
+            package_name: package_name,
+        }
+    }
+}
+
+impl Scaffold for CScaffold {
+    fn gen<P: AsRef<Path>>(&self, base_path: P) -> DefaultResult<()> {
+        fs::create_dir_all(base_path.as_ref().join("src"))?;
+
+        let templates = Templates::load()?;
+        let data = json!({ "name": self.package_name });
+
+        for (file_name, template) in &[
+            (MAKEFILE_FILE_NAME, "c/Makefile"),
+            (ZOME_C_PATH, "c/src/zome.c"),
+            (HDK_H_PATH, "c/src/hdk.h"),
+        ] {
+            fs::write(base_path.as_ref().join(file_name), templates.render(template, &data)?)?;
+        }
+
+        // create and fill in a build file which runs make
+        let build_file_path = base_path.as_ref().join(package::BUILD_CONFIG_FILE_NAME);
+
+        self.build_template.save_as(build_file_path)?;
+
+        Ok(())
+    }
+
+    fn install<P: AsRef<Path>>(&self, _base_path: P) -> DefaultResult<()> {
+        // C zomes have no dependencies to fetch
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_utils::gen_dir;
+
+    #[test]
+    fn gen_writes_makefile_project() {
+        let dir = gen_dir();
+        let code_path = dir.path().join("code");
+
+        CScaffold::new("greeter".into()).gen(&code_path).unwrap();
+
+        let makefile = fs::read_to_string(code_path.join(MAKEFILE_FILE_NAME)).unwrap();
+        let zome_c = fs::read_to_string(code_path.join(ZOME_C_PATH)).unwrap();
+        let build = Build::from_file(code_path.join(package::BUILD_CONFIG_FILE_NAME)).unwrap();
+
+        assert!(makefile.contains("greeter.wasm:"));
+        assert!(makefile.contains("--target=wasm32"));
+        assert!(zome_c.contains("HDK_EXPORT hdk_allocation __hdk_get_json_definition("));
+        assert!(code_path.join(HDK_H_PATH).exists());
+        assert_eq!(build.artifact, code_path.join("greeter.wasm").strip_prefix(&code_path).unwrap());
+        assert!(build.steps.contains_key("make"));
+    }
+}
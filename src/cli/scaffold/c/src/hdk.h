@@ -0,0 +1,39 @@
+#ifndef HDK_H
+#define HDK_H
+
+#include <stdint.h>
+
+/* Marks a function as exported from the WASM module, so Holochain can call it */
+#define HDK_EXPORT __attribute__((visibility("default")))
+
+/* Zome functions are called with the encoded allocation of their input and return the
+ * encoded allocation of their output: the offset in the WASM memory in the upper 16 bits
+ * and the length in the lower 16 bits. Returning 0 means success without any output. */
+typedef uint32_t hdk_allocation;
+
+static inline hdk_allocation hdk_encode_allocation(const char *offset, uint32_t length) {
+    return ((uint32_t)(uintptr_t)offset << 16) | (length & 0xffff);
+}
+
+static inline const char *hdk_allocation_offset(hdk_allocation allocation) {
+    return (const char *)(uintptr_t)(allocation >> 16);
+}
+
+static inline uint32_t hdk_allocation_length(hdk_allocation allocation) {
+    return allocation & 0xffff;
+}
+
+static inline uint32_t hdk_strlen(const char *text) {
+    uint32_t length = 0;
+    while (text[length] != '\0') {
+        length++;
+    }
+    return length;
+}
+
+/* Returns a constant string, such as a JSON literal, as the output of a zome function */
+static inline hdk_allocation hdk_return_str(const char *text) {
+    return hdk_encode_allocation(text, hdk_strlen(text));
+}
+
+#endif
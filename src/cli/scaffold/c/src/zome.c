@@ -0,0 +1,31 @@
+#include "hdk.h"
+
+/* The entry types and capabilities of the zome, as returned to `hc package`. Every
+ * function listed here has to be exported below. */
+static const char ZOME_DEFINITION[] =
+    "{"
+        "\"entry_types\":{},"
+        "\"capabilities\":{"
+            "\"main\":{"
+                "\"capability\":{\"membrane\":\"public\"},"
+                "\"functions\":["
+                    "{\"name\":\"hello\",\"inputs\":[],\"outputs\":[{\"name\":\"greeting\",\"type\":\"string\"}]}"
+                "]"
+            "}"
+        "}"
+    "}";
+
+HDK_EXPORT hdk_allocation __hdk_get_json_definition(hdk_allocation input) {
+    (void)input;
+    return hdk_return_str(ZOME_DEFINITION);
+}
+
+HDK_EXPORT hdk_allocation genesis(hdk_allocation input) {
+    (void)input;
+    return 0;
+}
+
+HDK_EXPORT hdk_allocation hello(hdk_allocation input) {
+    (void)input;
+    return hdk_return_str("{\"greeting\":\"Hello from {{name}}\"}");
+}
@@ -1,9 +1,15 @@
 pub mod rust;
 pub mod assemblyscript;
+pub mod c;
+pub mod plugin;
 
 use error::DefaultResult;
 use std::path::Path;
 
 pub trait Scaffold {
+    /// Writes the files of a new zome, without needing network access
     fn gen<P: AsRef<Path>>(&self, base_path: P) -> DefaultResult<()>;
+
+    /// Runs the package manager of the language to fetch the dependencies of the zome
+    fn install<P: AsRef<Path>>(&self, base_path: P) -> DefaultResult<()>;
 }
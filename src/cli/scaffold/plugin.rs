@@ -0,0 +1,380 @@
+use cli::{
+    generate::SUPPORTED_LANGUAGES,
+    package,
+    scaffold::Scaffold,
+    templates::Templates,
+};
+use config_files::Step;
+use error::DefaultResult;
+use serde_json;
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+    process::Command,
+};
+use util;
+
+/// External scaffolds are executables on the PATH named `hc-scaffold-<language>`
+pub const SCAFFOLD_EXECUTABLE_PREFIX: &str = "hc-scaffold-";
+
+/// The folder, inside the hc config folder, holding one template folder per language
+pub const LANGUAGES_DIR_NAME: &str = "languages";
+
+/// The descriptor that turns a folder inside the languages folder into a language
+pub const LANGUAGE_DESCRIPTOR_FILE_NAME: &str = "language.json";
+
+/// Describes a language given as a folder of templates
+#[derive(Clone, Default, Deserialize, Serialize)]
+pub struct LanguageDescriptor {
+    #[serde(default)]
+    pub description: Option<String>,
+    /// Fetches the dependencies of a generated zome with `hc generate --install`, run in
+    /// its code folder
+    #[serde(default)]
+    pub install: Option<Step>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum LanguageSource {
+    BuiltIn,
+    /// A folder inside `~/.config/hc/languages` with a language.json descriptor
+    Template(PathBuf),
+    /// An `hc-scaffold-<language>` executable
+    Executable(PathBuf),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Language {
+    pub name: String,
+    pub source: LanguageSource,
+}
+
+impl Language {
+    pub fn description(&self) -> Option<String> {
+        match self.source {
+            LanguageSource::BuiltIn => match self.name.as_str() {
+                "rust" => Some("Rust, built with cargo".into()),
+                "assemblyscript" => Some("AssemblyScript, built with asc".into()),
+                "c" => Some("C, built with clang and make".into()),
+                _ => None,
+            },
+            LanguageSource::Template(ref path) => read_descriptor(path)
+                .ok()
+                .and_then(|descriptor| descriptor.description),
+            // executables describe themselves on a single line
+            LanguageSource::Executable(ref path) => Command::new(path)
+                .arg("describe")
+                .output()
+                .ok()
+                .filter(|output| output.status.success())
+                .and_then(|output| String::from_utf8(output.stdout).ok())
+                .map(|description| description.trim().to_string())
+                .filter(|description| !description.is_empty()),
+        }
+    }
+}
+
+fn read_descriptor(template_path: &Path) -> DefaultResult<LanguageDescriptor> {
+    let descriptor_path = template_path.join(LANGUAGE_DESCRIPTOR_FILE_NAME);
+
+    serde_json::from_str(&fs::read_to_string(&descriptor_path)?)
+        .map_err(|err| format_err!("invalid language descriptor {:?}: {}", descriptor_path, err))
+}
+
+fn template_languages(languages_path: &Path) -> DefaultResult<Vec<Language>> {
+    if !languages_path.is_dir() {
+        return Ok(Vec::new());
+    }
+
+    let mut languages = Vec::new();
+
+    for entry in fs::read_dir(languages_path)? {
+        let path = entry?.path();
+
+        if path.join(LANGUAGE_DESCRIPTOR_FILE_NAME).is_file() {
+            languages.push(Language {
+                name: util::file_name_string(&path)?,
+                source: LanguageSource::Template(path),
+            });
+        }
+    }
+
+    Ok(languages)
+}
+
+fn executable_languages() -> Vec<Language> {
+    let paths = env::var_os("PATH").unwrap_or_default();
+    let mut languages = Vec::new();
+
+    for dir in env::split_paths(&paths) {
+        let entries = match fs::read_dir(&dir) {
+            Ok(entries) => entries,
+            Err(_) => continue,
+        };
+
+        for path in entries.filter_map(|entry| entry.ok()).map(|entry| entry.path()) {
+            let name = path
+                .file_stem()
+                .and_then(|stem| stem.to_str())
+                .filter(|stem| stem.starts_with(SCAFFOLD_EXECUTABLE_PREFIX))
+                .map(|stem| stem[SCAFFOLD_EXECUTABLE_PREFIX.len()..].to_string());
+
+            if let Some(name) = name {
+                if !name.is_empty() && util::is_executable(&path) {
+                    languages.push(Language {
+                        name,
+                        source: LanguageSource::Executable(path),
+                    });
+                }
+            }
+        }
+    }
+
+    languages
+}
+
+/// All languages zomes can be generated in: the built-in ones, then the template folders
+/// in `~/.config/hc/languages`, then the `hc-scaffold-<language>` executables in the
+/// order of the PATH. If a name is taken twice, the first one wins.
+pub fn languages() -> DefaultResult<Vec<Language>> {
+    let built_in = SUPPORTED_LANGUAGES.iter().map(|name| Language {
+        name: name.to_string(),
+        source: LanguageSource::BuiltIn,
+    });
+    let templates = template_languages(&util::config_dir()?.join(LANGUAGES_DIR_NAME))?;
+
+    let mut languages: Vec<Language> = Vec::new();
+
+    for language in built_in.chain(templates).chain(executable_languages()) {
+        if !languages.iter().any(|known| known.name == language.name) {
+            languages.push(language);
+        }
+    }
+
+    Ok(languages)
+}
+
+pub fn find_language(name: &str) -> DefaultResult<Language> {
+    languages()?
+        .into_iter()
+        .find(|language| language.name == name)
+        .ok_or_else(|| {
+            format_err!(
+                "unsupported language: {}, see hc generate --list-languages",
+                name
+            )
+        })
+}
+
+/// Scaffolds a zome by copying a folder of templates, rendering the file names and contents
+pub struct TemplateScaffold {
+    template_path: PathBuf,
+    package_name: String,
+}
+
+impl TemplateScaffold {
+    pub fn new(template_path: PathBuf, package_name: String) -> TemplateScaffold {
+        TemplateScaffold {
+            template_path,
+            package_name,
+        }
+    }
+
+    fn copy_dir(&self, templates: &Templates, source_path: &Path, dest_path: &Path) -> DefaultResult<()> {
+        let data = json!({ "name": self.package_name });
+
+        for entry in fs::read_dir(source_path)? {
+            let source = entry?.path();
+            let file_name = util::file_name_string(&source)?;
+
+            if source_path == self.template_path.as_path() && file_name == LANGUAGE_DESCRIPTOR_FILE_NAME {
+                continue;
+            }
+
+            let dest = dest_path.join(templates.render_template(&file_name, &data)?);
+
+            if source.is_dir() {
+                fs::create_dir_all(&dest)?;
+                self.copy_dir(templates, &source, &dest)?;
+            } else {
+                match String::from_utf8(fs::read(&source)?) {
+                    Ok(text) => fs::write(&dest, templates.render_template(&text, &data)?)?,
+                    // binary files get copied as they are
+                    Err(err) => fs::write(&dest, err.into_bytes())?,
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl Scaffold for TemplateScaffold {
+    fn gen<P: AsRef<Path>>(&self, base_path: P) -> DefaultResult<()> {
+        // validate the descriptor before writing anything
+        read_descriptor(&self.template_path)?;
+
+        fs::create_dir_all(&base_path)?;
+
+        self.copy_dir(&Templates::load()?, &self.template_path, base_path.as_ref())?;
+
+        ensure!(
+            base_path.as_ref().join(package::BUILD_CONFIG_FILE_NAME).is_file(),
+            "language template {:?} doesn't contain a {} file",
+            self.template_path,
+            package::BUILD_CONFIG_FILE_NAME
+        );
+
+        Ok(())
+    }
+
+    fn install<P: AsRef<Path>>(&self, base_path: P) -> DefaultResult<()> {
+        match read_descriptor(&self.template_path)?.install {
+            Some(step) => util::run_cmd(base_path.as_ref().to_path_buf(), step.bin, step.args),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Scaffolds a zome by calling an `hc-scaffold-<language>` executable, which gets called as
+/// `generate <code folder> <zome name>` and `install <code folder>`
+pub struct ExecutableScaffold {
+    executable_path: PathBuf,
+    package_name: String,
+}
+
+impl ExecutableScaffold {
+    pub fn new(executable_path: PathBuf, package_name: String) -> ExecutableScaffold {
+        ExecutableScaffold {
+            executable_path,
+            package_name,
+        }
+    }
+
+    fn run(&self, base_path: &Path, args: Vec<String>) -> DefaultResult<()> {
+        util::run_cmd(
+            base_path.to_path_buf(),
+            self.executable_path.to_string_lossy().into_owned(),
+            args,
+        )
+    }
+}
+
+impl Scaffold for ExecutableScaffold {
+    fn gen<P: AsRef<Path>>(&self, base_path: P) -> DefaultResult<()> {
+        fs::create_dir_all(&base_path)?;
+
+        let base_path = base_path.as_ref().canonicalize()?;
+
+        self.run(
+            &base_path,
+            vec![
+                "generate".to_owned(),
+                base_path.to_string_lossy().into_owned(),
+                self.package_name.clone(),
+            ],
+        )?;
+
+        ensure!(
+            base_path.join(package::BUILD_CONFIG_FILE_NAME).is_file(),
+            "{:?} didn't create a {} file",
+            self.executable_path,
+            package::BUILD_CONFIG_FILE_NAME
+        );
+
+        Ok(())
+    }
+
+    fn install<P: AsRef<Path>>(&self, base_path: P) -> DefaultResult<()> {
+        let base_path = base_path.as_ref().canonicalize()?;
+
+        self.run(
+            &base_path,
+            vec!["install".to_owned(), base_path.to_string_lossy().into_owned()],
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use config_files::Build;
+    use test_utils::gen_dir;
+
+    #[test]
+    fn template_languages_test() {
+        let languages = gen_dir();
+        let go_path = languages.path().join("go");
+
+        fs::create_dir_all(go_path.join("cmd")).unwrap();
+        fs::create_dir_all(languages.path().join("not-a-language")).unwrap();
+        fs::write(
+            go_path.join(LANGUAGE_DESCRIPTOR_FILE_NAME),
+            r#"{ "description": "Go, built with TinyGo" }"#,
+        ).unwrap();
+        fs::write(
+            go_path.join(package::BUILD_CONFIG_FILE_NAME),
+            r#"{ "steps": { "tinygo": ["build", "-o", "{{name}}.wasm"] }, "artifact": "{{name}}.wasm" }"#,
+        ).unwrap();
+        fs::write(go_path.join("cmd/{{name}}.go"), "package {{name}}\n").unwrap();
+
+        let found = template_languages(languages.path()).unwrap();
+
+        assert_eq!(
+            found,
+            vec![Language {
+                name: "go".into(),
+                source: LanguageSource::Template(go_path.clone()),
+            }]
+        );
+        assert_eq!(found[0].description(), Some("Go, built with TinyGo".into()));
+
+        let zome = gen_dir();
+        let code_path = zome.path().join("code");
+
+        TemplateScaffold::new(go_path, "blog".into()).gen(&code_path).unwrap();
+
+        let build = Build::from_file(code_path.join(package::BUILD_CONFIG_FILE_NAME)).unwrap();
+
+        assert_eq!(build.artifact, PathBuf::from("blog.wasm"));
+        assert_eq!(
+            fs::read_to_string(code_path.join("cmd/blog.go")).unwrap(),
+            "package blog\n"
+        );
+        assert!(!code_path.join(LANGUAGE_DESCRIPTOR_FILE_NAME).exists());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn executable_scaffold_test() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let bin = gen_dir();
+        let executable_path = bin.path().join("hc-scaffold-shell");
+
+        fs::write(
+            &executable_path,
+            "#!/bin/sh\n\
+             case \"$1\" in\n\
+             describe) echo 'Shell scripts' ;;\n\
+             generate) echo \"$3\" > \"$2/name\" && echo '{}' > \"$2/.build\" ;;\n\
+             esac\n",
+        ).unwrap();
+        fs::set_permissions(&executable_path, fs::Permissions::from_mode(0o755)).unwrap();
+
+        assert!(util::is_executable(&executable_path));
+
+        let language = Language {
+            name: "shell".into(),
+            source: LanguageSource::Executable(executable_path.clone()),
+        };
+        assert_eq!(language.description(), Some("Shell scripts".into()));
+
+        let zome = gen_dir();
+        let code_path = zome.path().join("code");
+
+        ExecutableScaffold::new(executable_path, "blog".into()).gen(&code_path).unwrap();
+
+        assert_eq!(fs::read_to_string(code_path.join("name")).unwrap(), "blog\n");
+    }
+}
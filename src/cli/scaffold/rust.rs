@@ -1,95 +1,122 @@
-use cli::{package, scaffold::Scaffold};
+use cli::{init::APP_CONFIG_FILE_NAME, package, scaffold::Scaffold, templates::Templates};
 use config_files::Build;
 use error::DefaultResult;
+use serde_json::Value;
 use std::{
-    fs::{self, OpenOptions},
-    io::{Read, Write, Seek, SeekFrom},
-    path::Path,
-};
-use toml::{
-    self,
-    value::{
-        Value
-    }
+    fs,
+    path::{Component, Path, PathBuf},
 };
+use toml;
 use util;
 
 pub const CARGO_FILE_NAME: &str = "Cargo.toml";
 pub const LIB_RS_PATH: &str = "src/lib.rs";
+pub const TARGET_DIR_NAME: &str = "target";
 
-pub struct RustScaffold {
-    build_template: Build,
-    package_name: String,
-}
+pub const DEFAULT_EDITION: &str = "2018";
+
+const WORKSPACE_KEY: &str = "workspace";
+const MEMBERS_KEY: &str = "members";
+
+/// Finds the Cargo workspace the crate at the given path belongs to, searching upwards
+/// until the root of the Holochain project, which is the folder containing the app.json
+pub fn find_workspace(crate_path: &Path) -> DefaultResult<Option<PathBuf>> {
+    for dir in crate_path.canonicalize()?.ancestors() {
+        let cargo_toml_path = dir.join(CARGO_FILE_NAME);
+
+        if cargo_toml_path.is_file() {
+            let cargo_toml: toml::Value = toml::from_str(&fs::read_to_string(&cargo_toml_path)?)?;
+
+            if cargo_toml.get(WORKSPACE_KEY).is_some() {
+                return Ok(Some(dir.to_path_buf()));
+            }
+        }
 
-/// Given existing Cargo.toml string, pull out some values and return a new
-/// string with values pulled from template
-fn generate_cargo_toml(name: &str, contents: &str) -> DefaultResult<String> {
-    let config: Value = toml::from_str(contents)?;
+        if dir.join(APP_CONFIG_FILE_NAME).is_file() {
+            break;
+        }
+    }
 
-    let authors_default = Value::from("[\"TODO\"]");
-    let edition_default = Value::from("\"TODO\"");
-    let maybe_package = config.get("package");
+    Ok(None)
+}
 
-    let name = Value::from(name);
-    let authors = maybe_package
-        .and_then(|p| p.get("authors"))
-        .unwrap_or(&authors_default);
-    let edition = maybe_package
-        .and_then(|p| p.get("edition"))
-        .unwrap_or(&edition_default);
+/// Adds the crate to the members of the workspace, unless it's already one of them
+pub fn add_workspace_member(workspace_path: &Path, crate_path: &Path) -> DefaultResult<()> {
+    let member = crate_path
+        .canonicalize()?
+        .strip_prefix(workspace_path.canonicalize()?)?
+        .components()
+        .map(|component| component.as_os_str().to_string_lossy().into_owned())
+        .collect::<Vec<_>>()
+        .join("/");
+
+    let cargo_toml_path = workspace_path.join(CARGO_FILE_NAME);
+    let mut cargo_toml: toml::Value = toml::from_str(&fs::read_to_string(&cargo_toml_path)?)?;
+
+    {
+        let workspace = cargo_toml
+            .get_mut(WORKSPACE_KEY)
+            .and_then(|workspace| workspace.as_table_mut())
+            .ok_or_else(|| format_err!("{:?} doesn't define a workspace", cargo_toml_path))?;
+
+        let members = workspace
+            .entry(MEMBERS_KEY.to_string())
+            .or_insert_with(|| toml::Value::Array(Vec::new()))
+            .as_array_mut()
+            .ok_or_else(|| format_err!("the workspace members in {:?} aren't a list", cargo_toml_path))?;
+
+        if members.iter().any(|known| known.as_str() == Some(member.as_str())) {
+            return Ok(());
+        }
 
-    interpolate_cargo_template(&name, authors, edition)
+        members.push(toml::Value::from(member));
+    }
+
+    fs::write(cargo_toml_path, toml::to_string(&cargo_toml)?)?;
+    Ok(())
 }
 
-/// Use the Cargo.toml.template file and interpolate values into the placeholders
-/// TODO: consider using an actual templating engine such as https://github.com/Keats/tera
-fn interpolate_cargo_template(name: &Value, authors: &Value, edition: &Value) -> DefaultResult<String> {
-    let template = include_str!("rust/Cargo.template.toml");
-    Ok(
-        template
-        .replace("<<NAME>>", toml::to_string(name)?.as_str())
-        .replace("<<AUTHORS>>", toml::to_string(authors)?.as_str())
-        .replace("<<EDITION>>", toml::to_string(edition)?.as_str())
-    )
+pub struct RustScaffold {
+    package_name: String,
+    authors: Vec<String>,
 }
 
 impl RustScaffold {
-    pub fn new(package_name: String) -> RustScaffold {
-        let artifact_name = format!("target/wasm32-unknown-unknown/release/{}.wasm", package_name);
+    pub fn new(package_name: String, authors: Vec<String>) -> RustScaffold {
         RustScaffold {
-            build_template: Build::with_artifact(artifact_name)
-                .cmd(
-                    "cargo",
-                    &["build", "--release", "--target=wasm32-unknown-unknown"],
-                ),
             package_name: package_name,
+            authors: authors,
         }
     }
 
-    /// Modify Cargo.toml in place, using pieces of the original
-    fn rewrite_cargo_toml(&self, base_path: &Path) -> DefaultResult<()> {
-        let cargo_file_path = base_path.join(CARGO_FILE_NAME);
-        let mut cargo_file = OpenOptions::new()
-            .read(true).write(true).open(cargo_file_path)?;
-        let mut contents = String::new();
-        cargo_file.read_to_string(&mut contents)?;
-
-        // create new Cargo.toml using pieces of the original
-        let new_toml = generate_cargo_toml(self.package_name.as_str(), contents.as_str())?;
-        cargo_file.seek(SeekFrom::Start(0))?;
-        cargo_file.write_all(new_toml.as_bytes())?;
-        Ok(())
+    /// Builds the crate, with the artifact in the given target folder, relative to the crate.
+    /// The debug profile skips the optimizations of a release build, to compile faster.
+    fn build_template(&self, target_path: &Path) -> Build {
+        let artifact_path = |profile: &str| {
+            target_path
+                .join("wasm32-unknown-unknown")
+                .join(profile)
+                .join(format!("{}.wasm", self.package_name))
+        };
+
+        Build::with_artifact(artifact_path("release"))
+            .cmd(
+                "cargo",
+                &["build", "--release", "--target=wasm32-unknown-unknown"],
+            )
+            .profile(
+                "debug",
+                Build::with_artifact(artifact_path("debug"))
+                    .cmd("cargo", &["build", "--target=wasm32-unknown-unknown"]),
+            )
     }
 
-    /// Completely rewrite src/lib.rs with custom scaffold file
-    fn rewrite_lib_rs(&self, base_path: &Path) -> DefaultResult<()> {
-        let file_path = base_path.join(LIB_RS_PATH);
-        let mut cargo_file = OpenOptions::new()
-            .truncate(true).write(true).open(file_path)?;
-        let contents = include_str!("./rust/lib.rs");
-        cargo_file.write_all(contents.as_bytes())?;
-        Ok(())
+    fn template_data(&self) -> Value {
+        json!({
+            "name": self.package_name,
+            "authors": self.authors,
+            "edition": DEFAULT_EDITION,
+        })
     }
 }
 
@@ -97,29 +124,112 @@ impl Scaffold for RustScaffold {
     fn gen<P: AsRef<Path>>(&self, base_path: P) -> DefaultResult<()> {
         fs::create_dir_all(&base_path)?;
 
-        // use cargo to initialise a library Rust crate without any version control
-        util::run_cmd(
-            base_path.as_ref().to_path_buf(),
-            "cargo".into(),
-            vec![
-                "init".to_owned(),
-                "--lib".to_owned(),
-                "--vcs".to_owned(),
-                "none".to_owned(),
-            ],
-        )?;
+        // render the crate files directly, so that no network access is needed
+        let templates = Templates::load()?;
+        let data = self.template_data();
 
-        // immediately rewrite the generated Cargo file, using some values
-        // and throwing away the rest
-        self.rewrite_cargo_toml(base_path.as_ref())?;
+        fs::write(
+            base_path.as_ref().join(CARGO_FILE_NAME),
+            templates.render("rust/Cargo.toml", &data)?,
+        )?;
 
-        // and clobber the autogenerated lib.rs with our own boilerplate
-        self.rewrite_lib_rs(base_path.as_ref())?;
+        let lib_rs_path = base_path.as_ref().join(LIB_RS_PATH);
+        if let Some(src_path) = lib_rs_path.parent() {
+            fs::create_dir_all(src_path)?;
+        }
+        fs::write(lib_rs_path, templates.render("rust/src/lib.rs", &data)?)?;
+
+        // crates in a workspace share its target folder
+        let target_path = match find_workspace(base_path.as_ref())? {
+            Some(workspace_path) => {
+                add_workspace_member(&workspace_path, base_path.as_ref())?;
+
+                let depth = base_path
+                    .as_ref()
+                    .canonicalize()?
+                    .strip_prefix(&workspace_path)?
+                    .components()
+                    .count();
+
+                (0..depth)
+                    .map(|_| Component::ParentDir.as_os_str())
+                    .collect::<PathBuf>()
+                    .join(TARGET_DIR_NAME)
+            }
+            None => PathBuf::from(TARGET_DIR_NAME),
+        };
 
         // create and fill in a build file appropriate for Rust
         let build_file_path = base_path.as_ref().join(package::BUILD_CONFIG_FILE_NAME);
-        self.build_template.save_as(build_file_path)?;
+        self.build_template(&target_path).save_as(build_file_path)?;
 
         Ok(())
     }
+
+    fn install<P: AsRef<Path>>(&self, base_path: P) -> DefaultResult<()> {
+        // download the dependencies, so that the zome can be built offline afterwards
+        util::run_cmd(
+            base_path.as_ref().to_path_buf(),
+            "cargo".into(),
+            vec!["fetch".to_owned()],
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_utils::gen_dir;
+    use toml;
+
+    #[test]
+    fn gen_writes_crate_offline() {
+        let dir = gen_dir();
+        let code_path = dir.path().join("code");
+
+        RustScaffold::new("blog".into(), vec!["Jane <jane@example.com>".into()])
+            .gen(&code_path)
+            .unwrap();
+
+        let cargo_toml: toml::Value = toml::from_str(&fs::read_to_string(code_path.join(CARGO_FILE_NAME)).unwrap()).unwrap();
+
+        assert_eq!(cargo_toml["package"]["name"].as_str(), Some("blog"));
+        assert_eq!(cargo_toml["package"]["edition"].as_str(), Some(DEFAULT_EDITION));
+        assert_eq!(
+            cargo_toml["package"]["authors"][0].as_str(),
+            Some("Jane <jane@example.com>")
+        );
+        assert!(code_path.join(LIB_RS_PATH).exists());
+        assert!(code_path.join(package::BUILD_CONFIG_FILE_NAME).exists());
+    }
+
+    #[test]
+    fn gen_joins_workspace() {
+        let dir = gen_dir();
+        fs::write(dir.path().join(APP_CONFIG_FILE_NAME), "{}").unwrap();
+        fs::write(dir.path().join(CARGO_FILE_NAME), "[workspace]\nmembers = []\n").unwrap();
+
+        let code_path = dir.path().join("zomes/blog/code");
+
+        RustScaffold::new("blog".into(), Vec::new()).gen(&code_path).unwrap();
+        // registering the same crate again doesn't duplicate it
+        add_workspace_member(dir.path(), &code_path).unwrap();
+
+        let workspace: toml::Value = toml::from_str(&fs::read_to_string(dir.path().join(CARGO_FILE_NAME)).unwrap()).unwrap();
+        let build = Build::from_file(code_path.join(package::BUILD_CONFIG_FILE_NAME)).unwrap();
+
+        assert_eq!(
+            workspace["workspace"]["members"],
+            toml::Value::from(vec!["zomes/blog/code"])
+        );
+        assert_eq!(
+            build.artifact,
+            PathBuf::from("../../../target/wasm32-unknown-unknown/release/blog.wasm")
+        );
+        assert_eq!(
+            build.for_profile("debug").artifact,
+            PathBuf::from("../../../target/wasm32-unknown-unknown/debug/blog.wasm")
+        );
+        assert_eq!(find_workspace(&code_path).unwrap(), Some(dir.path().canonicalize().unwrap()));
+    }
 }
@@ -0,0 +1,182 @@
+use error::DefaultResult;
+use handlebars::{self, Handlebars};
+use serde_json::{self, Value};
+use std::{collections::HashMap, fs, path::Path};
+use util;
+
+/// The folder, inside the hc config folder, holding templates that replace the built-in ones
+pub const TEMPLATES_DIR_NAME: &str = "templates";
+
+/// The built-in templates of the scaffolded files that take values. The names double as
+/// paths inside the templates folder, where a file of the same name overrides the built-in
+/// template.
+const BUILT_IN_TEMPLATES: &[(&str, &str)] = &[
+    ("rust/Cargo.toml", include_str!("scaffold/rust/Cargo.template.toml")),
+    ("assemblyscript/package.json", include_str!("scaffold/assemblyscript/package.template.json")),
+    ("c/Makefile", include_str!("scaffold/c/Makefile")),
+    ("c/src/zome.c", include_str!("scaffold/c/src/zome.c")),
+];
+
+/// The built-in scaffolded files without any values, which are written as they are. An
+/// override in the templates folder gets rendered as a template all the same.
+const BUILT_IN_FILES: &[(&str, &str)] = &[
+    ("rust/workspace/Cargo.toml", include_str!("scaffold/rust/Cargo.workspace.toml")),
+    ("rust/src/lib.rs", include_str!("scaffold/rust/lib.rs")),
+    ("assemblyscript/tsconfig.json", include_str!("scaffold/assemblyscript/tsconfig.json")),
+    ("assemblyscript/index.ts", include_str!("scaffold/assemblyscript/index-ts-starter.ts")),
+    ("c/src/hdk.h", include_str!("scaffold/c/src/hdk.h")),
+    ("gitignore", include_str!("git-scaffold/.gitignore")),
+    ("test/index.js", include_str!("js-tests-scaffold/index.js")),
+    ("test/package-lock.json", include_str!("js-tests-scaffold/package-lock.json")),
+    ("test/package.json", include_str!("js-tests-scaffold/package.json")),
+    ("test/README.md", include_str!("js-tests-scaffold/README.md")),
+    ("test/webpack.config.js", include_str!("js-tests-scaffold/webpack.config.js")),
+];
+
+// renders a value as a JSON literal, which is also a valid TOML string or array
+handlebars_helper!(json: |value: Json| serde_json::to_string(value).unwrap_or_default());
+
+/// Renders the scaffolded files with Handlebars, see https://handlebarsjs.com
+pub struct Templates {
+    registry: Handlebars,
+    /// The built-in files that aren't templates and weren't overridden
+    files: HashMap<&'static str, &'static str>,
+}
+
+impl Templates {
+    /// Loads the built-in templates, with the ones in `~/.config/hc/templates` taking precedence
+    pub fn load() -> DefaultResult<Templates> {
+        let overrides_path = util::config_dir()?.join(TEMPLATES_DIR_NAME);
+
+        Templates::load_from(&overrides_path)
+    }
+
+    pub fn load_from<P: AsRef<Path>>(overrides_path: P) -> DefaultResult<Templates> {
+        let mut registry = Handlebars::new();
+
+        // none of the scaffolded files are HTML, and a missing value is a mistake in the template
+        registry.register_escape_fn(handlebars::no_escape);
+        registry.set_strict_mode(true);
+        registry.register_helper("json", Box::new(json));
+
+        let mut files = HashMap::new();
+
+        for (name, built_in) in BUILT_IN_TEMPLATES {
+            let override_path = overrides_path.as_ref().join(name);
+
+            let template = if override_path.is_file() {
+                fs::read_to_string(&override_path)?
+            } else {
+                built_in.to_string()
+            };
+
+            registry
+                .register_template_string(name, template)
+                .map_err(|err| format_err!("invalid template {:?}: {}", name, err))?;
+        }
+
+        for (name, built_in) in BUILT_IN_FILES {
+            let override_path = overrides_path.as_ref().join(name);
+
+            if override_path.is_file() {
+                registry
+                    .register_template_string(name, fs::read_to_string(&override_path)?)
+                    .map_err(|err| format_err!("invalid template {:?}: {}", name, err))?;
+            } else {
+                files.insert(*name, *built_in);
+            }
+        }
+
+        Ok(Templates { registry, files })
+    }
+
+    pub fn render(&self, name: &str, data: &Value) -> DefaultResult<String> {
+        if let Some(file) = self.files.get(name) {
+            return Ok(file.to_string());
+        }
+
+        self.registry
+            .render(name, data)
+            .map_err(|err| format_err!("couldn't render template {:?}: {}", name, err))
+    }
+
+    /// Renders a template that isn't one of the registered ones, using the same helpers
+    pub fn render_template(&self, template: &str, data: &Value) -> DefaultResult<String> {
+        self.registry
+            .render_template(template, data)
+            .map_err(|err| format_err!("couldn't render template: {}", err))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_utils::gen_dir;
+    use toml;
+
+    #[test]
+    fn renders_built_in_templates() {
+        let dir = gen_dir();
+        let templates = Templates::load_from(dir.path()).unwrap();
+
+        let cargo_toml = templates
+            .render(
+                "rust/Cargo.toml",
+                &json!({
+                    "name": "blog",
+                    "authors": ["Jane <jane@example.com>", "John \"Jo\" Doe"],
+                    "edition": "2018",
+                }),
+            )
+            .unwrap();
+        let cargo_toml: toml::Value = toml::from_str(&cargo_toml).unwrap();
+
+        assert_eq!(cargo_toml["package"]["name"].as_str(), Some("blog"));
+        assert_eq!(cargo_toml["package"]["edition"].as_str(), Some("2018"));
+        assert_eq!(
+            cargo_toml["package"]["authors"][1].as_str(),
+            Some("John \"Jo\" Doe")
+        );
+
+        let package_json = templates
+            .render("assemblyscript/package.json", &json!({ "name": "blog" }))
+            .unwrap();
+        let package_json: Value = serde_json::from_str(&package_json).unwrap();
+
+        assert_eq!(package_json["name"], "blog");
+    }
+
+    #[test]
+    fn copies_built_in_files_without_values() {
+        let dir = gen_dir();
+        let templates = Templates::load_from(dir.path()).unwrap();
+
+        // written as they are, even if they happen to contain "{{"
+        for (name, built_in) in BUILT_IN_FILES {
+            assert_eq!(templates.render(name, &json!({})).unwrap(), *built_in);
+        }
+    }
+
+    #[test]
+    fn overrides_take_precedence() {
+        let dir = gen_dir();
+        fs::create_dir_all(dir.path().join("rust/src")).unwrap();
+        fs::write(
+            dir.path().join("rust/src/lib.rs"),
+            "// {{name}}\n{{#each entries}}// entry {{this}}\n{{/each}}{{#if public}}// public{{/if}}",
+        ).unwrap();
+
+        let templates = Templates::load_from(dir.path()).unwrap();
+
+        assert_eq!(
+            templates
+                .render(
+                    "rust/src/lib.rs",
+                    &json!({ "name": "blog", "entries": ["post", "comment"], "public": false }),
+                )
+                .unwrap(),
+            "// blog\n// entry post\n// entry comment\n"
+        );
+        assert!(templates.render("rust/src/lib.rs", &json!({})).is_err());
+    }
+}
@@ -1,18 +1,208 @@
-use cli::package;
+use cli::{
+    coverage::{
+        self, Coverage, CALL_MARKER, COVERAGE_ENV_VAR, COVERAGE_FILE_NAME,
+        INSTRUMENTED_ARTIFACT_FILE_NAME,
+    },
+    package,
+};
 use colored::*;
-use error::DefaultResult;
+use config_files::{Project, Step, Test as TestConfig, DEFAULT_PROFILE};
+use error::{DefaultResult, HolochainError};
+use ignore::WalkBuilder;
 use std::{fs, path::PathBuf};
 use util;
 
 pub const TEST_DIR_NAME: &str = "test";
 pub const DIST_DIR_NAME: &str = "dist";
 
+pub const FILTER_ENV_VAR: &str = "HC_TEST_FILTER";
+pub const ZOME_ENV_VAR: &str = "HC_TEST_ZOME";
+pub const LIST_ENV_VAR: &str = "HC_TEST_LIST";
+
+const NODE_MODULES_DIR_NAME: &str = "node_modules";
+
+/// Narrows down which tests get run or listed
+#[derive(Clone, Debug, Default)]
+pub struct TestSelection {
+    pub filter: Option<String>,
+    pub zome: Option<String>,
+}
+
+impl TestSelection {
+    /// The runner arguments for this selection, as configured in the test config
+    fn runner_args(&self, test_config: &TestConfig) -> Vec<String> {
+        let mut args = Vec::new();
+
+        if let Some(ref filter) = self.filter {
+            args.extend(test_config.filter_args.iter().map(|arg| arg.replace("{}", filter)));
+        }
+
+        if let Some(ref zome) = self.zome {
+            args.extend(test_config.zome_args.iter().map(|arg| arg.replace("{}", zome)));
+        }
+
+        args
+    }
+
+    /// The environment variables forwarding this selection to runners that have no
+    /// arguments configured for it
+    fn runner_env(&self) -> Vec<(String, String)> {
+        let mut env = Vec::new();
+
+        if let Some(ref filter) = self.filter {
+            env.push((FILTER_ENV_VAR.to_string(), filter.clone()));
+        }
+
+        if let Some(ref zome) = self.zome {
+            env.push((ZOME_ENV_VAR.to_string(), zome.clone()));
+        }
+
+        env
+    }
+
+    /// Warnings about the options of this selection that the runner only gets as
+    /// environment variables, since the test config has no runner arguments for them.
+    /// Runners that don't read those variables run all tests.
+    fn env_only_warnings(&self, test_config: &TestConfig) -> Vec<String> {
+        let options = [
+            ("--filter", self.filter.is_some(), &test_config.filter_args, "filter_args", FILTER_ENV_VAR),
+            ("--zome", self.zome.is_some(), &test_config.zome_args, "zome_args", ZOME_ENV_VAR),
+        ];
+
+        options
+            .iter()
+            .filter(|(_, given, args, _, _)| *given && args.is_empty())
+            .map(|(option, _, _, args_key, env_var)| {
+                format!(
+                    "the test config has no {} for the runner {}, so {} is only passed to it as {} \
+                     and all tests run unless the runner reads it",
+                    args_key, test_config.runner.bin, option, env_var
+                )
+            }).collect()
+    }
+
+    fn warn_if_env_only(&self, test_config: &TestConfig) {
+        for warning in self.env_only_warnings(test_config) {
+            log!("{} {}", "Warning:".yellow().bold(), warning);
+        }
+    }
+
+    /// Whether a test found by `discover_tests` is part of this selection. A test belongs
+    /// to a zome if its file, or any folder it is in, is named after the zome, or if its
+    /// name starts with `zome/`, as the stubs added by `hc generate` do.
+    fn matches(&self, test: &DiscoveredTest) -> bool {
+        let matches_filter = self
+            .filter
+            .as_ref()
+            .map(|filter| test.name.contains(filter.as_str()))
+            .unwrap_or(true);
+
+        let matches_zome = self
+            .zome
+            .as_ref()
+            .map(|zome| {
+                test.name.starts_with(&format!("{}/", zome))
+                    || test.file.file_stem().map(|stem| stem == zome.as_str()).unwrap_or(false)
+                    || test
+                        .file
+                        .parent()
+                        .map(|dir| dir.iter().any(|component| component == zome.as_str()))
+                        .unwrap_or(false)
+            }).unwrap_or(true);
+
+        matches_filter && matches_zome
+    }
+}
+
+/// A test case found by statically scanning the test folder
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct DiscoveredTest {
+    /// The file containing the test, relative to the test folder
+    pub file: PathBuf,
+    pub name: String,
+}
+
+/// Runs a single prep step inside the given folder, unless the path it creates is already there
+fn run_step(base_path: &PathBuf, step: &Step) -> DefaultResult<()> {
+    if let Some(ref creates) = step.creates {
+        if base_path.join(creates).exists() {
+            return Ok(());
+        }
+    }
+
+    util::run_cmd(base_path.clone(), step.bin.clone(), step.args.clone())
+}
+
+/// Ensures the test folder exists and runs the prep steps inside it
+fn prepare(path: &PathBuf, test_config: &TestConfig, skip_prep: bool) -> DefaultResult<()> {
+    let tests_path = path.join(&test_config.dir);
+    ensure!(
+        tests_path.exists(),
+        "Directory {} does not exist",
+        test_config.dir
+    );
+
+    if !skip_prep && !test_config.prep.is_empty() {
+        log!("{} tests in {}", "Preparing".green().bold(), test_config.dir);
+
+        for step in &test_config.prep {
+            run_step(&tests_path, step)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Starts the configured runner from the project root, with the artifact as the last argument.
+/// If calls get recorded, the lines printed by the coverage recorder are taken out of the
+/// runner's output and collected.
+fn run_runner(
+    path: &PathBuf,
+    test_config: &TestConfig,
+    extra_args: Vec<String>,
+    env: &[(String, String)],
+    calls: Option<&mut Vec<String>>,
+) -> DefaultResult<()> {
+    let mut runner_args = test_config.runner.args.clone();
+    runner_args.extend(extra_args);
+
+    if let Some(ref artifact) = test_config.artifact {
+        runner_args.push(artifact.to_string_lossy().into_owned());
+    }
+
+    let bin = test_config.runner.bin.clone();
+
+    let result = match calls {
+        Some(calls) => util::run_cmd_filtering_stdout(path.to_path_buf(), bin, runner_args, env, |line| {
+            if line.starts_with(CALL_MARKER) {
+                calls.push(line[CALL_MARKER.len()..].to_string());
+                false
+            } else {
+                true
+            }
+        }),
+        None => util::run_cmd_with_env(path.to_path_buf(), bin, runner_args, env),
+    };
+
+    result.map_err(|err| match err.downcast::<HolochainError>() {
+        // failing tests aren't a build error
+        Ok(HolochainError::BuildStep(step)) => format_err!("the tests failed, {}", step),
+        Ok(err) => err.into(),
+        Err(err) => err,
+    })
+}
+
+/// Runs the tests. If a coverage threshold is given, the zome functions called by the tests
+/// get recorded, and the run fails if less than that percentage of them got called. A
+/// JavaScript artifact gets the coverage recorder put in front of it, other runners can
+/// write the calls to the file named by `HC_COVERAGE_FILE`.
 pub fn test(
     path: &PathBuf,
-    tests_folder: &str,
-    testfile: &str,
-    skip_npm: bool,
+    test_config: &TestConfig,
+    selection: &TestSelection,
+    skip_prep: bool,
     skip_build: bool,
+    coverage_threshold: Option<f64>,
 ) -> DefaultResult<()> {
     // create dist folder
     let dist_path = path.join(&DIST_DIR_NAME);
@@ -24,56 +214,227 @@ pub fn test(
     if !skip_build {
         // build the package file, within the dist folder
         let bundle_file_path = dist_path.join(package::DEFAULT_BUNDLE_FILE_NAME);
-        println!(
+        log!(
             "{} files for testing to file: {:?}",
             "Packaging".green().bold(),
             bundle_file_path
         );
-        package(true, Some(bundle_file_path.to_path_buf()))?;
+        let limits = Project::load(path)?.package.limits;
+        let profile = test_config
+            .profile
+            .as_ref()
+            .map(String::as_str)
+            .unwrap_or(DEFAULT_PROFILE);
+
+        package(path, true, Some(bundle_file_path.to_path_buf()), &limits, false, profile)?;
     }
 
     // build tests
-    let tests_path = path.join(&tests_folder);
+    prepare(path, test_config, skip_prep)?;
+
+    // execute the tests using the configured runner
+    if let Some(ref artifact) = test_config.artifact {
+        log!("{} tests in {:?}", "Running".green().bold(), artifact);
+    } else {
+        log!("{} tests", "Running".green().bold());
+    }
+
+    let mut env = selection.runner_env();
+    let mut test_config = test_config.clone();
+
+    let coverage_file_path = fs::canonicalize(&dist_path)?.join(COVERAGE_FILE_NAME);
+
+    if coverage_threshold.is_some() {
+        if coverage_file_path.exists() {
+            fs::remove_file(&coverage_file_path)?;
+        }
+
+        env.push((
+            COVERAGE_ENV_VAR.to_string(),
+            coverage_file_path.to_string_lossy().into_owned(),
+        ));
+
+        let js_artifact = test_config
+            .artifact
+            .clone()
+            .filter(|artifact| artifact.extension().map(|ext| ext == "js").unwrap_or(false));
+
+        if let Some(artifact) = js_artifact {
+            let instrumented_path = dist_path.join(INSTRUMENTED_ARTIFACT_FILE_NAME);
+            coverage::instrument_artifact(&path.join(artifact), &instrumented_path)?;
+            test_config.artifact = Some(instrumented_path);
+        }
+    }
+
+    selection.warn_if_env_only(&test_config);
+
+    let mut calls = Vec::new();
+    run_runner(
+        path,
+        &test_config,
+        selection.runner_args(&test_config),
+        &env,
+        coverage_threshold.map(|_| &mut calls),
+    )?;
+
+    if let Some(threshold) = coverage_threshold {
+        let mut coverage =
+            Coverage::from_bundle_file(&dist_path.join(package::DEFAULT_BUNDLE_FILE_NAME))?;
+
+        coverage.record_calls(&calls.join("\n"));
+
+        if coverage_file_path.exists() {
+            coverage.record_calls(&fs::read_to_string(&coverage_file_path)?);
+        }
+
+        if !coverage.has_calls() {
+            log!(
+                "{} no calls to zome functions were recorded, neither through Container.loadAndInstantiate \
+                 in the test artifact nor by the runner in {}",
+                "Warning:".yellow().bold(),
+                COVERAGE_ENV_VAR
+            );
+        }
+
+        coverage.print_report();
+
+        ensure!(
+            coverage.percentage() >= threshold,
+            "coverage of {:.1}% is below the threshold of {:.1}%",
+            coverage.percentage(),
+            threshold
+        );
+    }
+
+    Ok(())
+}
+
+/// Lists the selected tests without running them. Runners that are able to list their
+/// tests get asked through the `list_args` of the test config, otherwise the test folder
+/// gets scanned for test cases, which are returned.
+pub fn list(
+    path: &PathBuf,
+    test_config: &TestConfig,
+    selection: &TestSelection,
+    skip_prep: bool,
+) -> DefaultResult<Option<Vec<DiscoveredTest>>> {
+    if !test_config.list_args.is_empty() {
+        prepare(path, test_config, skip_prep)?;
+
+        let mut args = selection.runner_args(test_config);
+        args.extend(test_config.list_args.iter().cloned());
+
+        let mut env = selection.runner_env();
+        env.push((LIST_ENV_VAR.to_string(), "1".to_string()));
+
+        selection.warn_if_env_only(test_config);
+
+        return run_runner(path, test_config, args, &env, None).map(|_| None);
+    }
+
+    let tests_path = path.join(&test_config.dir);
     ensure!(
         tests_path.exists(),
         "Directory {} does not exist",
-        tests_folder
+        test_config.dir
     );
 
-    if !skip_npm {
-        // npm install, if no node_modules yet
-        let node_modules_path = tests_path.join("node_modules");
-        if !node_modules_path.exists() {
-            println!("{}", "Installing node_modules".green().bold());
-            util::run_cmd(
-                tests_path.clone(),
-                "npm".to_string(),
-                vec!["install".to_string(), "--silent".to_string()],
-            )?;
-        }
-
-        // npm run build
-        println!(
-            "{} an executable test file: {}",
-            "Building".green().bold(),
-            testfile,
-        );
-        util::run_cmd(
-            tests_path.clone(),
-            "npm".to_string(),
-            vec!["run".to_string(), "build".to_string()],
-        )?;
-    }
-
-    // execute the built test file using hcshell
-    println!("{} tests in {}", "Running".green().bold(), testfile,);
-    util::run_cmd(
-        path.to_path_buf(),
-        "hcshell".to_string(),
-        vec![testfile.to_string()],
-    )?;
+    let tests: Vec<_> = discover_tests(&tests_path)?
+        .into_iter()
+        .filter(|test| selection.matches(test))
+        .collect();
 
-    Ok(())
+    for test in &tests {
+        log!("{}: {}", test.file.display(), test.name);
+    }
+
+    log!("{} {} tests", "Found".green().bold(), tests.len());
+
+    Ok(Some(tests))
+}
+
+/// Scans the JavaScript, TypeScript and Rust files in the test folder for test cases
+pub fn discover_tests(tests_path: &PathBuf) -> DefaultResult<Vec<DiscoveredTest>> {
+    let mut tests = Vec::new();
+
+    for entry in WalkBuilder::new(tests_path).build() {
+        let file_path = entry?.path().to_path_buf();
+
+        let relative_path = file_path.strip_prefix(tests_path)?.to_path_buf();
+        let is_build_output = relative_path
+            .iter()
+            .any(|component| component == NODE_MODULES_DIR_NAME || component == DIST_DIR_NAME);
+
+        if !file_path.is_file() || is_build_output {
+            continue;
+        }
+
+        let names = match file_path.extension().and_then(|ext| ext.to_str()) {
+            Some("js") | Some("ts") => js_test_names(&fs::read_to_string(&file_path)?),
+            Some("rs") => rust_test_names(&fs::read_to_string(&file_path)?),
+            _ => continue,
+        };
+
+        tests.extend(names.into_iter().map(|name| DiscoveredTest {
+            file: relative_path.clone(),
+            name,
+        }));
+    }
+
+    Ok(tests)
+}
+
+/// Finds the names of `test("name", ...)` calls, as used by tape, mocha and jest
+fn js_test_names(source: &str) -> Vec<String> {
+    let mut names = Vec::new();
+
+    for (index, _) in source.match_indices("test(") {
+        let is_call = source[..index]
+            .chars()
+            .last()
+            .map(|c| !(c.is_alphanumeric() || c == '_' || c == '$' || c == '.'))
+            .unwrap_or(true);
+
+        if !is_call {
+            continue;
+        }
+
+        let mut rest = source[index + "test(".len()..].trim_start().chars();
+
+        if let Some(quote) = rest.next().filter(|c| *c == '"' || *c == '\'' || *c == '`') {
+            let name: String = rest.by_ref().take_while(|c| *c != quote).collect();
+            names.push(name);
+        }
+    }
+
+    names
+}
+
+/// Finds the names of functions annotated with `#[test]`
+fn rust_test_names(source: &str) -> Vec<String> {
+    let mut names = Vec::new();
+    let mut lines = source.lines().map(|line| line.trim());
+
+    while let Some(line) = lines.next() {
+        if line != "#[test]" {
+            continue;
+        }
+
+        if let Some(fn_line) = lines.find(|line| !line.starts_with("#[") && !line.starts_with("//")) {
+            let name: String = fn_line
+                .trim_start_matches("pub ")
+                .trim_start_matches("fn ")
+                .chars()
+                .take_while(|c| c.is_alphanumeric() || *c == '_')
+                .collect();
+
+            if !name.is_empty() {
+                names.push(name);
+            }
+        }
+    }
+
+    names
 }
 
 #[cfg(test)]
@@ -82,16 +443,7 @@ pub mod tests {
     use assert_cmd::prelude::*;
     use cli::package;
     use std::process::Command;
-    use tempfile::{Builder, TempDir};
-
-    const HOLOCHAIN_TEST_PREFIX: &str = "org.holochain.test";
-
-    fn gen_dir() -> TempDir {
-        Builder::new()
-            .prefix(HOLOCHAIN_TEST_PREFIX)
-            .tempdir()
-            .unwrap()
-    }
+    use test_utils::gen_dir;
 
     #[test]
     fn test_command_basic_test() {
@@ -106,13 +458,7 @@ pub mod tests {
             .assert()
             .success();
 
-        test(
-            &temp_dir_path_buf,
-            &TEST_DIR_NAME,
-            "test/dist/bundle.js",
-            false,
-            false,
-        )
+        test(&temp_dir_path_buf, &TestConfig::default(), &TestSelection::default(), false, false, None)
         .unwrap_or_else(|e| panic!("test call failed: {}", e));
 
         // check success of packaging step
@@ -145,13 +491,12 @@ pub mod tests {
             .assert()
             .success();
 
-        let result = test(
-            &temp_dir_path_buf,
-            &TEST_DIR_NAME,
-            "test/dist/index.js",
-            true,
-            false,
-        );
+        let test_config = TestConfig {
+            artifact: Some(PathBuf::from("test/dist/index.js")),
+            ..TestConfig::default()
+        };
+
+        let result = test(&temp_dir_path_buf, &test_config, &TestSelection::default(), true, false, None);
 
         // is err because "hcshell test/dist/index.js" will have failed
         // but the important thing is that the npm calls weren't made
@@ -173,6 +518,31 @@ pub mod tests {
             .exists());
     }
 
+    #[test]
+    fn test_command_custom_runner() {
+        let temp_space = gen_dir();
+        let temp_dir_path = temp_space.path();
+        let temp_dir_path_buf = temp_space.path().to_path_buf();
+
+        // do init first, so theres a project
+        Command::main_binary()
+            .unwrap()
+            .args(&["init", temp_dir_path.to_str().unwrap()])
+            .assert()
+            .success();
+
+        let test_config = TestConfig {
+            prep: vec![Step::new("touch", &["prepared"])],
+            runner: Step::new("test", &["-f"]),
+            artifact: Some(PathBuf::from("test/prepared")),
+            ..TestConfig::default()
+        };
+
+        // "test -f test/prepared" only succeeds if the prep step ran
+        test(&temp_dir_path_buf, &test_config, &TestSelection::default(), false, true, None)
+            .unwrap_or_else(|e| panic!("test call failed: {}", e));
+    }
+
     #[test]
     fn test_command_no_test_folder() {
         let temp_space = gen_dir();
@@ -186,15 +556,195 @@ pub mod tests {
             .assert()
             .success();
 
-        let result = test(
-            &temp_dir_path_buf,
-            "west",
-            "test/dist/bundle.js",
-            false,
-            false,
-        );
+        let test_config = TestConfig {
+            dir: "west".into(),
+            ..TestConfig::default()
+        };
+
+        let result = test(&temp_dir_path_buf, &test_config, &TestSelection::default(), false, false, None);
 
         // should err because "west" directory doesn't exist
         assert!(result.is_err());
     }
+
+    #[test]
+    fn test_command_forwards_selection() {
+        let temp_space = gen_dir();
+        let temp_dir_path = temp_space.path();
+        let temp_dir_path_buf = temp_space.path().to_path_buf();
+
+        // do init first, so theres a project
+        Command::main_binary()
+            .unwrap()
+            .args(&["init", temp_dir_path.to_str().unwrap()])
+            .assert()
+            .success();
+
+        let test_config = TestConfig {
+            prep: Vec::new(),
+            runner: Step::new("sh", &["-c", "test \"$HC_TEST_FILTER\" = \"$0\""]),
+            artifact: None,
+            filter_args: vec!["{}".into()],
+            ..TestConfig::default()
+        };
+        let selection = TestSelection {
+            filter: Some("create post".into()),
+            zome: None,
+        };
+
+        // the filter reaches the runner both as an argument and as an environment variable
+        test(&temp_dir_path_buf, &test_config, &selection, false, true, None)
+            .unwrap_or_else(|e| panic!("test call failed: {}", e));
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn records_coverage_with_the_default_runner() {
+        use std::{env, os::unix::fs::PermissionsExt, path::Path};
+
+        // the stand-in for hcshell runs on node
+        if util::find_executable(Path::new(""), "node").is_none() {
+            return;
+        }
+
+        let temp_space = gen_dir();
+        let root = temp_space.path();
+
+        fs::write(root.join("app.json"), "{}").unwrap();
+        fs::create_dir_all(root.join(DIST_DIR_NAME)).unwrap();
+        fs::write(
+            root.join(DIST_DIR_NAME).join(package::DEFAULT_BUNDLE_FILE_NAME),
+            json!({
+                "zomes": {
+                    "blog": {
+                        "capabilities": {
+                            "main": {
+                                "functions": [{ "name": "create_post" }, { "name": "get_post" }]
+                            }
+                        }
+                    }
+                }
+            }).to_string(),
+        ).unwrap();
+
+        // a test artifact as built from the scaffolded tests
+        fs::create_dir_all(root.join("test/dist")).unwrap();
+        fs::write(
+            root.join("test/dist/bundle.js"),
+            "var app = Container.loadAndInstantiate('dist/bundle.json')\n\
+             app.start()\n\
+             console.log('ok 1 create post')\n\
+             app.call('blog', 'main', 'create_post', '{}')\n",
+        ).unwrap();
+
+        // provides Container to the script it runs, like hcshell does
+        let bin_path = root.join("bin");
+        fs::create_dir_all(&bin_path).unwrap();
+        fs::write(
+            bin_path.join("hcshell"),
+            "#!/usr/bin/env node\n\
+             global.Container = { loadAndInstantiate: function () {\n\
+               return { start: function () {}, call: function () { return '{}' } }\n\
+             } }\n\
+             require('vm').runInThisContext(require('fs').readFileSync(process.argv[2], 'utf8'))\n",
+        ).unwrap();
+        fs::set_permissions(bin_path.join("hcshell"), fs::Permissions::from_mode(0o755)).unwrap();
+
+        let path = format!("{}:{}", bin_path.display(), env::var("PATH").unwrap_or_default());
+
+        let output = Command::main_binary()
+            .unwrap()
+            .current_dir(root)
+            .env("PATH", &path)
+            .args(&["test", "--no-package", "--skip-npm", "--coverage-threshold", "50"])
+            .output()
+            .unwrap();
+
+        let stdout = String::from_utf8_lossy(&output.stdout);
+
+        assert!(output.status.success(), "{}", stdout);
+        assert!(stdout.contains("ok 1 create post"));
+        assert!(stdout.contains("blog: 1/2 functions called"));
+        assert!(stdout.contains("uncovered get_post"));
+        assert!(stdout.contains("total: 50.0%"));
+        assert!(!stdout.contains(CALL_MARKER));
+
+        Command::main_binary()
+            .unwrap()
+            .current_dir(root)
+            .env("PATH", &path)
+            .args(&["test", "--no-package", "--skip-npm", "--coverage-threshold", "60"])
+            .assert()
+            .failure();
+    }
+
+    #[test]
+    fn warns_about_selections_the_runner_only_gets_as_env_vars() {
+        let selection = TestSelection {
+            filter: Some("create post".into()),
+            zome: Some("blog".into()),
+        };
+
+        // the default runner, hcshell, has no arguments configured for either
+        let warnings = selection.env_only_warnings(&TestConfig::default());
+
+        assert_eq!(warnings.len(), 2);
+        assert!(warnings[0].starts_with("the test config has no filter_args for the runner hcshell"));
+        assert!(warnings[1].contains("--zome is only passed to it as HC_TEST_ZOME"));
+
+        let test_config = TestConfig {
+            filter_args: vec!["--grep".into(), "{}".into()],
+            zome_args: vec!["--zome".into(), "{}".into()],
+            ..TestConfig::default()
+        };
+
+        assert!(selection.env_only_warnings(&test_config).is_empty());
+        assert!(TestSelection::default().env_only_warnings(&TestConfig::default()).is_empty());
+    }
+
+    #[test]
+    fn discover_tests_test() {
+        let temp_space = gen_dir();
+        let tests_path = temp_space.path().to_path_buf();
+
+        fs::create_dir_all(tests_path.join("blog")).unwrap();
+        fs::create_dir_all(tests_path.join("node_modules/tape")).unwrap();
+        fs::write(
+            tests_path.join("blog/index.js"),
+            "test('create post', (t) => {\n  t.test(\"nested\", () => {})\n})\ntest(\"get post\", () => {})\n",
+        ).unwrap();
+        fs::write(
+            tests_path.join("node_modules/tape/index.js"),
+            "test('not a project test', () => {})",
+        ).unwrap();
+        fs::write(
+            tests_path.join("users.rs"),
+            "#[test]\nfn can_register() {}\n\n#[test]\n#[ignore]\npub fn can_login() {}\n",
+        ).unwrap();
+
+        let mut tests = discover_tests(&tests_path).unwrap();
+        tests.sort_by(|a, b| a.name.cmp(&b.name));
+
+        assert_eq!(
+            tests,
+            vec![
+                DiscoveredTest { file: PathBuf::from("users.rs"), name: "can_login".into() },
+                DiscoveredTest { file: PathBuf::from("users.rs"), name: "can_register".into() },
+                DiscoveredTest { file: PathBuf::from("blog/index.js"), name: "create post".into() },
+                DiscoveredTest { file: PathBuf::from("blog/index.js"), name: "get post".into() },
+            ]
+        );
+
+        let selection = TestSelection {
+            filter: Some("post".into()),
+            zome: Some("blog".into()),
+        };
+        assert_eq!(tests.iter().filter(|test| selection.matches(test)).count(), 2);
+
+        let selection = TestSelection {
+            filter: None,
+            zome: Some("users".into()),
+        };
+        assert_eq!(tests.iter().filter(|test| selection.matches(test)).count(), 2);
+    }
 }
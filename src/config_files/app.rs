@@ -19,17 +19,27 @@ pub struct Author {
     signature: String,
 }
 
+impl Author {
+    pub fn new<S: Into<String>>(identifier: S) -> Author {
+        Author {
+            indentifier: identifier.into(),
+            public_key_source: "".into(),
+            signature: "".into(),
+        }
+    }
+
+    pub fn identifier(&self) -> &str {
+        &self.indentifier
+    }
+}
+
 impl Default for App {
     fn default() -> Self {
         App {
             name: "Holochain App Name".into(),
             description: "A Holochain app".into(),
             version: Version::new(0, 1, 0),
-            authors: vec![Author {
-                indentifier: "Author Name <author@name.com>".into(),
-                public_key_source: "".into(),
-                signature: "".into(),
-            }],
+            authors: vec![Author::new("Author Name <author@name.com>")],
             dht: Dht {},
             properties: Default::default(),
         }
@@ -1,26 +1,230 @@
 use base64;
-use error::DefaultResult;
+use cli::BUILD_CONFIG_FILE_NAME;
+use colored::*;
+use error::{DefaultResult, HolochainError};
+use serde::{
+    de::{MapAccess, Visitor},
+    Deserialize, Deserializer, Serialize, Serializer,
+};
 use serde_json;
 use std::{
-    collections::HashMap,
+    collections::BTreeMap,
+    env,
+    fmt,
     fs::File,
     io::Read,
     path::{Path, PathBuf},
+    slice,
 };
 use util;
+use wasm;
+
+/// The profile builds use unless another one is chosen
+pub const DEFAULT_PROFILE: &str = "release";
 
 #[derive(Clone, Deserialize, Serialize)]
 pub struct Build {
-    pub steps: HashMap<String, Vec<String>>,
+    pub steps: BuildSteps,
+    /// The path of the built WASM file, in which variables get expanded like in the steps
     pub artifact: PathBuf,
+    /// Optimizes the artifact after the build steps, before it gets bundled
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub optimize: Option<Optimize>,
+    /// Replace the steps, artifact or optimizations when building with one of these profiles
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub profiles: BTreeMap<String, Profile>,
+}
+
+/// The parts of a build that differ for a profile, the others are taken from the build
+#[derive(Clone, Default, Deserialize, Serialize)]
+pub struct Profile {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub steps: Option<BuildSteps>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub artifact: Option<PathBuf>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub optimize: Option<Optimize>,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+/// The optimizations applied to a WASM artifact
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+pub struct Optimize {
+    /// Removes custom sections, like the function names used for debugging
+    #[serde(default = "default_true")]
+    pub strip_custom_sections: bool,
+    /// Removes all code that can't be reached from one of the exports
+    #[serde(default = "default_true")]
+    pub dead_code_elimination: bool,
+    /// Runs wasm-opt from binaryen with these arguments, if it's installed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub wasm_opt: Option<Vec<String>>,
+}
+
+/// The arguments of a build step, given on their own or together with environment variables
+/// set for the step. Variables like `${ZOME_NAME}` get expanded in both.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+#[serde(untagged)]
+pub enum BuildStep {
+    Args(Vec<String>),
+    WithEnv {
+        #[serde(default)]
+        args: Vec<String>,
+        #[serde(default)]
+        env: BTreeMap<String, String>,
+    },
+}
+
+impl BuildStep {
+    pub fn args(&self) -> &[String] {
+        match self {
+            BuildStep::Args(args) | BuildStep::WithEnv { args, .. } => args,
+        }
+    }
+
+    pub fn env(&self) -> Vec<(&String, &String)> {
+        match self {
+            BuildStep::Args(_) => Vec::new(),
+            BuildStep::WithEnv { env, .. } => env.iter().collect(),
+        }
+    }
+}
+
+/// The build steps, keyed by their command, which run in the order the .build file lists them
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct BuildSteps(Vec<(String, BuildStep)>);
+
+impl BuildSteps {
+    /// Adds a step after the others, or replaces the step of the same command
+    pub fn insert(&mut self, bin: String, step: BuildStep) {
+        match self.0.iter().position(|(existing, _)| *existing == bin) {
+            Some(index) => self.0[index].1 = step,
+            None => self.0.push((bin, step)),
+        }
+    }
+
+    pub fn contains_key(&self, bin: &str) -> bool {
+        self.0.iter().any(|(existing, _)| existing == bin)
+    }
+}
+
+impl<'a> IntoIterator for &'a BuildSteps {
+    type Item = &'a (String, BuildStep);
+    type IntoIter = slice::Iter<'a, (String, BuildStep)>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.iter()
+    }
+}
+
+impl Serialize for BuildSteps {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_map(self.0.iter().map(|(bin, step)| (bin, step)))
+    }
+}
+
+/// Reads the steps as a JSON object, keeping the order of its keys
+struct BuildStepsVisitor;
+
+impl<'de> Visitor<'de> for BuildStepsVisitor {
+    type Value = BuildSteps;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "an object of commands and their arguments")
+    }
+
+    fn visit_map<M: MapAccess<'de>>(self, mut map: M) -> Result<BuildSteps, M::Error> {
+        let mut steps = BuildSteps::default();
+
+        while let Some((bin, step)) = map.next_entry()? {
+            steps.insert(bin, step);
+        }
+
+        Ok(steps)
+    }
+}
+
+impl<'de> Deserialize<'de> for BuildSteps {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<BuildSteps, D::Error> {
+        deserializer.deserialize_map(BuildStepsVisitor)
+    }
+}
+
+/// What the build of a zome depends on besides its .build file
+#[derive(Clone, Debug)]
+pub struct BuildContext {
+    pub zome_name: String,
+    pub project_root: PathBuf,
+    pub profile: String,
+    /// The file the output of the build steps gets appended to
+    pub log_file: PathBuf,
+}
+
+impl BuildContext {
+    /// The value of a variable in a .build file, falling back to the environment variables
+    fn variable(&self, name: &str) -> Option<String> {
+        match name {
+            "ZOME_NAME" => Some(self.zome_name.clone()),
+            "PROJECT_ROOT" => Some(self.project_root.to_string_lossy().into_owned()),
+            "PROFILE" => Some(self.profile.clone()),
+            _ => env::var(name).ok(),
+        }
+    }
+
+    /// Replaces each `${NAME}` in the text with the value of the variable, and each `$${`
+    /// with a literal `${`
+    pub fn expand(&self, text: &str) -> Result<String, String> {
+        let mut expanded = String::new();
+        let mut rest = text;
+
+        while let Some(start) = rest.find("${") {
+            if rest[..start].ends_with('$') {
+                expanded.push_str(&rest[..start - 1]);
+                expanded.push_str("${");
+                rest = &rest[start + 2..];
+                continue;
+            }
+
+            expanded.push_str(&rest[..start]);
+
+            let end = rest[start..]
+                .find('}')
+                .ok_or_else(|| format!("unclosed variable in {:?}", text))?;
+            let name = &rest[start + 2..start + end];
+
+            let value = self
+                .variable(name)
+                .ok_or_else(|| format!("unknown variable ${{{}}} in {:?}", name, text))?;
+            expanded.push_str(&value);
+
+            rest = &rest[start + end + 1..];
+        }
+
+        expanded.push_str(rest);
+        Ok(expanded)
+    }
+}
+
+impl Default for Optimize {
+    fn default() -> Self {
+        Optimize {
+            strip_custom_sections: true,
+            dead_code_elimination: true,
+            wasm_opt: None,
+        }
+    }
 }
 
 impl Build {
     /// Creates a Build struct from a .build JSON file and returns it
     pub fn from_file<T: AsRef<Path>>(path: T) -> DefaultResult<Build> {
-        let file = File::open(path)?;
+        let path = path.as_ref();
+        let file = File::open(path).map_err(|err| HolochainError::config(path, err))?;
 
-        let build = serde_json::from_reader(&file)?;
+        let build = serde_json::from_reader(&file).map_err(|err| HolochainError::config(path, err))?;
 
         Ok(build)
     }
@@ -33,33 +237,122 @@ impl Build {
         Ok(())
     }
 
-    /// Starts the build using the supplied build steps and returns the contents of the artifact
-    pub fn run(&self, base_path: &PathBuf) -> DefaultResult<String> {
-        for (bin, args) in &self.steps {
-            util::run_cmd(base_path.to_path_buf(), bin.to_string(), args.clone())?;
+    /// Starts the build using the supplied build steps and returns the contents of the artifact.
+    /// The output of the steps gets appended to the log file of the zome.
+    pub fn run(&self, base_path: &PathBuf, context: &BuildContext) -> DefaultResult<String> {
+        let expand = |text: &str| {
+            context
+                .expand(text)
+                .map_err(|err| HolochainError::config(base_path.join(BUILD_CONFIG_FILE_NAME), err))
+        };
+
+        for (bin, step) in &self.steps {
+            let args = step
+                .args()
+                .iter()
+                .map(|arg| expand(arg))
+                .collect::<Result<Vec<_>, _>>()?;
+            let env = step
+                .env()
+                .into_iter()
+                .map(|(name, value)| Ok((name.clone(), expand(value)?)))
+                .collect::<Result<Vec<_>, HolochainError>>()?;
+
+            let duration = util::run_build_step(
+                base_path,
+                bin,
+                &args,
+                &env,
+                &context.zome_name,
+                &context.log_file,
+            )?;
+
+            log!(
+                "{} {} of zome {:?} in {:.2}s",
+                "Finished".green().bold(),
+                bin,
+                context.zome_name,
+                util::seconds(duration)
+            );
         }
 
-        let artifact_path = base_path.join(&self.artifact);
+        let artifact_path = base_path.join(expand(&self.artifact.to_string_lossy())?);
 
         if artifact_path.exists() && artifact_path.is_file() {
             let mut wasm_buf = Vec::new();
             File::open(&artifact_path)?.read_to_end(&mut wasm_buf)?;
 
+            if let Some(ref optimize) = self.optimize {
+                let optimized = wasm::optimize(&wasm_buf, optimize)
+                    .map_err(|err| HolochainError::Wasm(format!("unable to optimize {:?}: {}", artifact_path, err)))?;
+
+                log!(
+                    "{} {:?} from {} to {} bytes",
+                    "Optimized".green().bold(),
+                    self.artifact,
+                    wasm_buf.len(),
+                    optimized.len()
+                );
+
+                wasm_buf = optimized;
+            }
+
             Ok(base64::encode(&wasm_buf))
         } else {
-            bail!("artifact path either doesn't point to a file or doesn't exist")
+            Err(HolochainError::Wasm(format!(
+                "artifact path {:?} either doesn't point to a file or doesn't exist",
+                artifact_path
+            )).into())
         }
     }
 
+    /// The build to run for the given profile. Profiles the build doesn't define use its
+    /// steps and artifact as they are, in which `${PROFILE}` can still tell them apart.
+    pub fn for_profile(&self, profile: &str) -> Build {
+        let mut build = self.clone();
+
+        if let Some(overrides) = build.profiles.remove(profile) {
+            if let Some(steps) = overrides.steps {
+                build.steps = steps;
+            }
+
+            if let Some(artifact) = overrides.artifact {
+                build.artifact = artifact;
+            }
+
+            if let Some(optimize) = overrides.optimize {
+                build.optimize = Some(optimize);
+            }
+        }
+
+        build.profiles.clear();
+        build
+    }
+
     pub fn with_artifact<P: Into<PathBuf>>(artifact: P) -> Build {
         let path: PathBuf = artifact.into();
 
         Build {
-            steps: HashMap::new(),
+            steps: BuildSteps::default(),
             artifact: path,
+            optimize: None,
+            profiles: BTreeMap::new(),
         }
     }
 
+    /// Adds a profile that builds with the steps, artifact and optimizations of the given build
+    pub fn profile<S: Into<String>>(mut self, name: S, build: Build) -> Build {
+        self.profiles.insert(
+            name.into(),
+            Profile {
+                steps: Some(build.steps),
+                artifact: Some(build.artifact),
+                optimize: build.optimize,
+            },
+        );
+        self
+    }
+
     pub fn cmd<S: Into<String> + Clone>(mut self, cmd: S, args: &[S]) -> Build {
         let cmd: String = cmd.into();
         let args: Vec<_> = args
@@ -70,7 +363,7 @@ impl Build {
                 arg
             }).collect();
 
-        self.steps.insert(cmd, args);
+        self.steps.insert(cmd, BuildStep::Args(args));
         self
     }
 }
@@ -1,7 +1,9 @@
 mod app;
 mod build;
 mod dht;
+mod project;
 
 pub use self::app::{App, Author};
-pub use self::build::Build;
+pub use self::build::{Build, BuildContext, Optimize, DEFAULT_PROFILE};
 pub use self::dht::Dht;
+pub use self::project::{Project, Step, Test};
@@ -0,0 +1,189 @@
+use cli::{APP_CONFIG_FILE_NAME, PROJECT_CONFIG_FILE_NAME, TEST_DIR_NAME};
+use error::{DefaultResult, HolochainError};
+use sandbox::Limits;
+use serde_json;
+use std::{
+    fs::File,
+    env,
+    path::{Path, PathBuf},
+};
+
+/// Project wide settings of a Holochain app that are not part of the DNA itself
+#[derive(Clone, Default, Deserialize, Serialize)]
+pub struct Project {
+    #[serde(default)]
+    pub test: Test,
+    #[serde(default)]
+    pub package: Package,
+}
+
+/// Settings of `hc package`
+#[derive(Clone, Default, Deserialize, Serialize)]
+pub struct Package {
+    /// Bounds for running the zomes to extract their definitions
+    #[serde(default)]
+    pub limits: Limits,
+}
+
+/// Describes how `hc test` prepares and executes the test suite
+#[derive(Clone, Deserialize, Serialize)]
+pub struct Test {
+    /// The folder containing the tests, relative to the project root
+    pub dir: String,
+    /// Commands run in order inside the test folder before the runner is started
+    #[serde(default)]
+    pub prep: Vec<Step>,
+    /// The command executing the tests, run from the project root
+    pub runner: Step,
+    /// The file produced by the prep steps that gets passed to the runner as its last argument
+    #[serde(default)]
+    pub artifact: Option<PathBuf>,
+    /// Runner arguments added for `--filter`, `{}` gets replaced by the pattern
+    #[serde(default)]
+    pub filter_args: Vec<String>,
+    /// Runner arguments added for `--zome`, `{}` gets replaced by the zome name
+    #[serde(default)]
+    pub zome_args: Vec<String>,
+    /// Runner arguments making it list the tests instead of running them
+    #[serde(default)]
+    pub list_args: Vec<String>,
+    /// The build profile the app gets packaged with for the tests, `release` if not given
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub profile: Option<String>,
+}
+
+#[derive(Clone, Deserialize, Serialize)]
+pub struct Step {
+    pub bin: String,
+    #[serde(default)]
+    pub args: Vec<String>,
+    /// The step gets skipped if this path (relative to the step's working directory) exists
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub creates: Option<PathBuf>,
+}
+
+impl Step {
+    pub fn new<S: Into<String> + Clone>(bin: S, args: &[S]) -> Step {
+        Step {
+            bin: bin.into(),
+            args: args.iter().map(|arg| arg.clone().into()).collect(),
+            creates: None,
+        }
+    }
+
+    pub fn creates<P: Into<PathBuf>>(mut self, path: P) -> Step {
+        self.creates = Some(path.into());
+        self
+    }
+}
+
+impl Default for Test {
+    fn default() -> Self {
+        Test {
+            dir: TEST_DIR_NAME.into(),
+            prep: vec![
+                Step::new("npm", &["install", "--silent"]).creates("node_modules"),
+                Step::new("npm", &["run", "build"]),
+            ],
+            runner: Step::new("hcshell", &[]),
+            // this "magic string" comes from the webpack config
+            // in the js-tests-scaffold: https://github.com/holochain/js-tests-scaffold/blob/master/webpack.config.js#L5-L8
+            // they need to stay in sync
+            artifact: Some(PathBuf::from("test/dist/bundle.js")),
+            filter_args: Vec::new(),
+            zome_args: Vec::new(),
+            list_args: Vec::new(),
+            profile: None,
+        }
+    }
+}
+
+impl Project {
+    /// Creates a Project struct from a project config JSON file and returns it
+    pub fn from_file<T: AsRef<Path>>(path: T) -> DefaultResult<Project> {
+        let path = path.as_ref();
+        let file = File::open(path).map_err(|err| HolochainError::config(path, err))?;
+
+        let project = serde_json::from_reader(&file).map_err(|err| HolochainError::config(path, err))?;
+
+        Ok(project)
+    }
+
+    /// Reads the project config inside the given project root, or falls back to the
+    /// defaults if there is none
+    pub fn load(root: &PathBuf) -> DefaultResult<Project> {
+        let config_path = root.join(PROJECT_CONFIG_FILE_NAME);
+
+        if config_path.exists() {
+            Project::from_file(config_path)
+        } else {
+            Ok(Project::default())
+        }
+    }
+
+    /// The root of the project the commands work on: the given project folder, or else the
+    /// closest folder containing an app.json, starting at the working directory and going up
+    pub fn root(project_dir: Option<&PathBuf>) -> DefaultResult<PathBuf> {
+        match project_dir {
+            Some(project_dir) => {
+                ensure!(project_dir.is_dir(), "the project folder {:?} doesn't exist", project_dir);
+                Ok(project_dir.canonicalize()?)
+            }
+            None => Project::find_root(&env::current_dir()?),
+        }
+    }
+
+    /// Finds the closest folder containing an app.json, like cargo does with Cargo.toml
+    pub fn find_root(dir: &Path) -> DefaultResult<PathBuf> {
+        let dir = dir.canonicalize()?;
+
+        let root = dir
+            .ancestors()
+            .find(|ancestor| ancestor.join(APP_CONFIG_FILE_NAME).is_file())
+            .map(Path::to_path_buf)
+            .ok_or_else(|| {
+                HolochainError::config(
+                    &dir,
+                    format!(
+                        "neither this folder nor any of its parents contains an {}, \
+                         run hc init or choose the project with --project-dir",
+                        APP_CONFIG_FILE_NAME
+                    ),
+                )
+            })?;
+
+        Ok(root)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use test_utils::gen_dir;
+
+    #[test]
+    fn finds_the_root_from_subfolders() {
+        let dir = gen_dir();
+        let code_path = dir.path().join("zomes/blog/code");
+        fs::create_dir_all(&code_path).unwrap();
+        fs::write(dir.path().join(APP_CONFIG_FILE_NAME), "{}").unwrap();
+
+        let root = dir.path().canonicalize().unwrap();
+
+        assert_eq!(Project::find_root(&code_path).unwrap(), root);
+        assert_eq!(Project::find_root(dir.path()).unwrap(), root);
+        assert_eq!(Project::root(Some(&code_path)).unwrap(), code_path.canonicalize().unwrap());
+    }
+
+    #[test]
+    fn fails_outside_of_projects() {
+        let dir = gen_dir();
+
+        let err = HolochainError::from(Project::find_root(dir.path()).unwrap_err());
+
+        assert_eq!(err.exit_code(), 3);
+        assert!(err.to_string().contains("run hc init or choose the project with --project-dir"));
+        assert!(Project::root(Some(&dir.path().join("missing"))).is_err());
+    }
+}
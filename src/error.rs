@@ -1,10 +1,185 @@
+use cli::Check;
 use failure::Error;
+use std::{fmt, io, path::PathBuf, time::Duration};
+use util;
 
+/// The errors `hc` tells apart, each exiting with its own code so that scripts can react
 #[derive(Debug, Fail)]
 pub enum HolochainError {
     #[fail(display = "Error: {}", _0)]
     Default(Error),
+    /// A config file, like `.hcconfig`, `.build` or `app.json`, can't be read
+    #[fail(display = "Config error in {:?}: {}", path, message)]
+    Config { path: PathBuf, message: String },
+    /// A build step, or any other external command, didn't succeed
+    #[fail(display = "Build error: {}", _0)]
+    BuildStep(BuildStepError),
+    /// A WASM artifact is missing, invalid or fails while its definition gets extracted
+    #[fail(display = "WASM error: {}", _0)]
+    Wasm(String),
+    /// A bundle can't be unpacked
+    #[fail(display = "Bundle format error: {}", _0)]
+    Bundle(String),
+    #[fail(display = "IO error: {}", _0)]
+    Io(#[cause] io::Error),
+    /// Tools the project needs are missing or outdated, with the checks of all of them
+    #[fail(display = "Toolchain error: {}", message)]
+    Toolchain { message: String, checks: Vec<Check> },
+}
+
+/// The command of a failed build step and how it exited
+#[derive(Debug)]
+pub struct BuildStepError {
+    pub command: String,
+    /// `None` if the command was terminated by a signal
+    pub exit_code: Option<i32>,
+    /// What was captured while running the build step of a zome
+    pub captured: Option<CapturedStep>,
+}
+
+#[derive(Debug)]
+pub struct CapturedStep {
+    pub zome: String,
+    pub duration: Duration,
+    /// The file holding the whole output of the zome's build
+    pub log_file: PathBuf,
+    pub stderr_tail: Vec<String>,
+}
+
+impl fmt::Display for BuildStepError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if let Some(ref captured) = self.captured {
+            write!(f, "zome {:?}: ", captured.zome)?;
+        }
+
+        match self.exit_code {
+            Some(exit_code) => write!(f, "command `{}` exited with code {}", self.command, exit_code)?,
+            None => write!(f, "command `{}` was terminated by a signal", self.command)?,
+        }
+
+        if let Some(ref captured) = self.captured {
+            write!(f, " after {:.2}s", util::seconds(captured.duration))?;
+
+            if !captured.stderr_tail.is_empty() {
+                write!(f, "\nlast lines of stderr:")?;
+
+                for line in &captured.stderr_tail {
+                    write!(f, "\n    {}", line)?;
+                }
+            }
+
+            write!(f, "\nthe whole output is in {}", captured.log_file.display())?;
+        }
+
+        Ok(())
+    }
+}
+
+impl HolochainError {
+    pub fn config<P: Into<PathBuf>, E: fmt::Display>(path: P, err: E) -> HolochainError {
+        HolochainError::Config {
+            path: path.into(),
+            message: err.to_string(),
+        }
+    }
+
+    /// The name of the kind of error, as it's reported with `--output json`
+    pub fn kind(&self) -> &'static str {
+        match self {
+            HolochainError::Default(_) => "other",
+            HolochainError::Config { .. } => "config",
+            HolochainError::BuildStep(_) => "build_step",
+            HolochainError::Wasm(_) => "wasm",
+            HolochainError::Bundle(_) => "bundle",
+            HolochainError::Io(_) => "io",
+            HolochainError::Toolchain { .. } => "toolchain",
+        }
+    }
+
+    /// The exit code of `hc` for this error, as documented in the README
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            HolochainError::Default(_) => 1,
+            HolochainError::Config { .. } => 3,
+            HolochainError::BuildStep(_) => 4,
+            HolochainError::Wasm(_) => 5,
+            HolochainError::Bundle(_) => 6,
+            HolochainError::Io(_) => 7,
+            HolochainError::Toolchain { .. } => 8,
+        }
+    }
+
+    /// The checks of the tools, reported along with the error by `--output json`
+    pub fn checks(&self) -> Option<&[Check]> {
+        match self {
+            HolochainError::Toolchain { checks, .. } => Some(checks),
+            _ => None,
+        }
+    }
+}
+
+/// Recovers the typed errors raised deep inside the commands, which get passed up as
+/// `failure::Error`s
+impl From<Error> for HolochainError {
+    fn from(err: Error) -> Self {
+        let err = match err.downcast::<HolochainError>() {
+            Ok(err) => return err,
+            Err(err) => err,
+        };
+
+        match err.downcast::<io::Error>() {
+            Ok(err) => HolochainError::Io(err),
+            Err(err) => HolochainError::Default(err),
+        }
+    }
 }
 
 pub type DefaultResult<T> = Result<T, Error>;
 pub type HolochainResult<T> = Result<T, HolochainError>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn recovers_typed_errors() {
+        let err: Error = HolochainError::Wasm("not a valid WASM module".into()).into();
+        assert_eq!(HolochainError::from(err).exit_code(), 5);
+
+        let err: Error = io::Error::new(io::ErrorKind::NotFound, "no such file").into();
+        assert_eq!(HolochainError::from(err).exit_code(), 7);
+
+        let err = format_err!("something else");
+        assert_eq!(HolochainError::from(err).exit_code(), 1);
+    }
+
+    #[test]
+    fn describes_build_steps() {
+        let err = HolochainError::BuildStep(BuildStepError {
+            command: "cargo build".into(),
+            exit_code: Some(101),
+            captured: None,
+        });
+
+        assert_eq!(err.to_string(), "Build error: command `cargo build` exited with code 101");
+        assert_eq!(err.exit_code(), 4);
+
+        let err = HolochainError::BuildStep(BuildStepError {
+            command: "cargo build".into(),
+            exit_code: Some(101),
+            captured: Some(CapturedStep {
+                zome: "blog".into(),
+                duration: Duration::from_millis(1500),
+                log_file: PathBuf::from(".hc/logs/blog.log"),
+                stderr_tail: vec!["error: aborting".into()],
+            }),
+        });
+
+        assert_eq!(
+            err.to_string(),
+            "Build error: zome \"blog\": command `cargo build` exited with code 101 after 1.50s\n\
+             last lines of stderr:\n    error: aborting\n\
+             the whole output is in .hc/logs/blog.log"
+        );
+    }
+}
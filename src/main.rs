@@ -1,6 +1,4 @@
 extern crate holochain_core_types;
-extern crate holochain_cas_implementations;
-extern crate holochain_core;
 extern crate holochain_dna;
 extern crate structopt;
 #[macro_use]
@@ -10,27 +8,80 @@ extern crate serde;
 extern crate serde_derive;
 extern crate toml;
 extern crate assert_cmd;
+extern crate atty;
 extern crate base64;
 extern crate colored;
+extern crate dirs;
 extern crate dir_diff;
+#[macro_use]
+extern crate handlebars;
 extern crate semver;
+extern crate sha2;
 #[macro_use]
 extern crate serde_json;
 extern crate ignore;
+extern crate parity_wasm;
+extern crate pwasm_utils;
 extern crate tempfile;
 extern crate uuid;
+extern crate wasmi;
 
+#[macro_use]
+mod util;
 mod cli;
 mod config_files;
 mod error;
-mod util;
+mod sandbox;
+mod wasm;
+#[cfg(test)]
+mod test_utils;
 
+use config_files::{Project, DEFAULT_PROFILE};
 use error::{HolochainError, HolochainResult};
-use std::path::PathBuf;
+use semver::Version;
+use serde_json::Value;
+use std::{io, path::PathBuf, str::FromStr};
 use structopt::StructOpt;
 
 #[derive(StructOpt)]
 #[structopt(about = "A command line for Holochain")]
+struct Opt {
+    #[structopt(
+        long = "output",
+        default_value = "human",
+        raw(possible_values = "&[\"human\", \"json\"]"),
+        help = "Prints a single JSON object with the result or the error to stdout, and everything else to stderr, given before the command"
+    )]
+    output: OutputFormat,
+    #[structopt(
+        long = "project-dir",
+        parse(from_os_str),
+        help = "The root of the Holochain app, defaults to the closest folder containing an app.json, starting at the working directory"
+    )]
+    project_dir: Option<PathBuf>,
+    #[structopt(subcommand)]
+    command: Cli,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum OutputFormat {
+    Human,
+    Json,
+}
+
+impl FromStr for OutputFormat {
+    type Err = String;
+
+    fn from_str(format: &str) -> Result<Self, Self::Err> {
+        match format {
+            "human" => Ok(OutputFormat::Human),
+            "json" => Ok(OutputFormat::Json),
+            other => Err(format!("unknown output format: {}", other)),
+        }
+    }
+}
+
+#[derive(StructOpt)]
 enum Cli {
     #[structopt(
         name = "web",
@@ -47,6 +98,11 @@ enum Cli {
         about = "Starts a Holochain node as an agent"
     )]
     Agent,
+    #[structopt(
+        name = "doctor",
+        about = "Checks that the tools needed to build and test the current Holochain app are installed"
+    )]
+    Doctor,
     #[structopt(
         name = "package",
         alias = "p",
@@ -58,8 +114,41 @@ enum Cli {
             help = "Strips all __META__ sections off the target bundle. Makes unpacking of the bundle impossible"
         )]
         strip_meta: bool,
-        #[structopt(long = "output", short = "o", parse(from_os_str))]
-        output: Option<PathBuf>,
+        #[structopt(
+            long = "bundle",
+            short = "o",
+            alias = "output",
+            parse(from_os_str),
+            help = "The file to write the bundle to, defaults to bundle.json in the root of the app, also accepted as the deprecated --output"
+        )]
+        bundle: Option<PathBuf>,
+        #[structopt(
+            long = "fuel",
+            help = "How many instructions a zome may execute to return its definition, overrides package.limits.fuel of .hcconfig"
+        )]
+        fuel: Option<u64>,
+        #[structopt(
+            long = "max-memory-pages",
+            help = "How many pages of 64 KiB of memory a zome may use to return its definition, overrides package.limits.max_memory_pages of .hcconfig"
+        )]
+        max_memory_pages: Option<u32>,
+        #[structopt(
+            long = "timeout",
+            help = "How many seconds a zome may take to return its definition, overrides package.limits.timeout_secs of .hcconfig"
+        )]
+        timeout: Option<u64>,
+        #[structopt(
+            long = "verbose",
+            short = "v",
+            help = "Prints the debug messages of each zome, which are always shown when a zome fails"
+        )]
+        verbose: bool,
+        #[structopt(
+            long = "profile",
+            raw(default_value = "DEFAULT_PROFILE"),
+            help = "The build profile, like debug or release, selecting the steps and artifacts of the .build files"
+        )]
+        profile: String,
     },
     #[structopt(
         name = "unpack",
@@ -79,6 +168,41 @@ enum Cli {
     Init {
         #[structopt(parse(from_os_str))]
         path: PathBuf,
+        #[structopt(
+            long = "template",
+            help = "A project template to start from: the path of a folder, a git URL or the name of a template in ~/.config/hc/project-templates"
+        )]
+        template: Option<String>,
+        #[structopt(long = "name", help = "The name of the app, defaults to the folder name")]
+        name: Option<String>,
+        #[structopt(long = "author", help = "An author of the app, can be given multiple times")]
+        authors: Vec<String>,
+        #[structopt(long = "description", help = "A short description of the app")]
+        description: Option<String>,
+        #[structopt(long = "version", help = "The initial version of the app, defaults to 0.1.0")]
+        version: Option<Version>,
+        #[structopt(
+            long = "zome",
+            help = "A zome to generate, given as name or name:language, can be given multiple times"
+        )]
+        zomes: Vec<cli::ZomeDetails>,
+        #[structopt(
+            long = "yes",
+            short = "y",
+            help = "Don't ask for the app details, but use the given values or the defaults"
+        )]
+        yes: bool,
+        #[structopt(
+            long = "merge",
+            alias = "force",
+            help = "Allows a non-empty directory, only creating the missing files and never overwriting existing ones"
+        )]
+        merge: bool,
+        #[structopt(
+            long = "no-workspace",
+            help = "Doesn't create a Cargo workspace for the Rust zomes, so that each of them is built on its own"
+        )]
+        no_workspace: bool,
     },
     #[structopt(
         name = "generate",
@@ -90,12 +214,29 @@ enum Cli {
             help = "The path to the zome that should be generated (usually in ./zomes/)",
             parse(from_os_str)
         )]
-        zome: PathBuf,
+        zome: Option<PathBuf>,
         #[structopt(
             help = "The language of the generated zome",
             default_value = "rust"
         )]
         language: String,
+        #[structopt(
+            long = "name",
+            help = "The name of the zome, defaults to the last component of the zome path"
+        )]
+        name: Option<String>,
+        #[structopt(
+            long = "install",
+            help = "Runs cargo or npm after generating the zome, to fetch its dependencies"
+        )]
+        install: bool,
+        #[structopt(
+            long = "list-languages",
+            help = "Lists the languages zomes can be generated in, including the ones added as plugins"
+        )]
+        list_languages: bool,
+        #[structopt(subcommand)]
+        item: Option<GenerateItem>,
     },
     #[structopt(
         name = "test",
@@ -118,7 +259,7 @@ enum Cli {
         #[structopt(
             long = "skip-npm",
             short = "s",
-            help = "Skip npm install and npm run build, defaults to false",
+            help = "Skip the test prep steps (npm install and npm run build by default), defaults to false",
         )]
         skip_npm: bool,
         #[structopt(
@@ -127,44 +268,220 @@ enum Cli {
             help = "Skip packaging DNA",
         )]
         skip_build: bool,
+        #[structopt(
+            long = "filter",
+            short = "f",
+            help = "Only run the tests whose name contains the given pattern",
+        )]
+        filter: Option<String>,
+        #[structopt(
+            long = "zome",
+            short = "z",
+            help = "Only run the tests of the given zome",
+        )]
+        zome: Option<String>,
+        #[structopt(
+            long = "list",
+            short = "l",
+            help = "List the selected tests instead of running them",
+        )]
+        list: bool,
+        #[structopt(
+            long = "coverage",
+            short = "c",
+            help = "Report which zome functions were not called by the tests",
+        )]
+        coverage: bool,
+        #[structopt(
+            long = "coverage-threshold",
+            help = "Fail if less than the given percentage of zome functions were called, implies --coverage",
+        )]
+        coverage_threshold: Option<f64>,
+        #[structopt(
+            long = "profile",
+            help = "The build profile the app gets packaged with, defaults to the profile of the test config or release",
+        )]
+        profile: Option<String>,
+    },
+}
+
+#[derive(StructOpt)]
+enum GenerateItem {
+    #[structopt(
+        name = "function",
+        about = "Adds a zome function to an existing zome"
+    )]
+    Function {
+        #[structopt(
+            help = "The zome to add the function to, either its path or its name in the zomes folder of the app",
+            parse(from_os_str)
+        )]
+        zome: PathBuf,
+        #[structopt(help = "The name of the function")]
+        name: String,
+        #[structopt(
+            long = "capability",
+            default_value = "main",
+            help = "The capability the function belongs to"
+        )]
+        capability: String,
+        #[structopt(
+            long = "inputs",
+            help = "The parameters of the function, each given as name:Type"
+        )]
+        inputs: Vec<String>,
+        #[structopt(
+            long = "returns",
+            alias = "output",
+            help = "The type of the result, defaults to serde_json::Value in Rust and string in AssemblyScript, also accepted as the deprecated --output"
+        )]
+        returns: Option<String>,
+    },
+    #[structopt(
+        name = "entry",
+        about = "Adds an entry type to an existing zome"
+    )]
+    Entry {
+        #[structopt(
+            help = "The zome to add the entry type to, either its path or its name in the zomes folder of the app",
+            parse(from_os_str)
+        )]
+        zome: PathBuf,
+        #[structopt(help = "The name of the entry type")]
+        name: String,
+        #[structopt(
+            long = "fields",
+            help = "The fields of the entry, each given as name:Type"
+        )]
+        fields: Vec<String>,
     },
 }
 
 fn main() {
-    if let Err(err) = run() {
-        eprintln!("{}", err);
+    let opt = Opt::from_args();
+    let json_output = opt.output == OutputFormat::Json;
 
-        ::std::process::exit(1);
+    util::set_json_output(json_output);
+
+    match run(opt.command, opt.project_dir) {
+        Ok(result) => {
+            if json_output {
+                println!("{}", json!({ "success": true, "result": result }));
+            }
+        }
+        Err(err) => {
+            if json_output {
+                let mut error = json!({
+                    "code": err.exit_code(),
+                    "kind": err.kind(),
+                    "message": err.to_string(),
+                });
+
+                // hc doctor reports which tools failed and how to fix them
+                if let Some(checks) = err.checks() {
+                    error["checks"] = json!(checks);
+                }
+
+                println!("{}", json!({ "success": false, "error": error }));
+            } else {
+                eprintln!("{}", err);
+            }
+
+            ::std::process::exit(err.exit_code());
+        }
     }
 }
 
-fn run() -> HolochainResult<()> {
-    let args = Cli::from_args();
+/// Runs the command and returns its result, which gets printed with `--output json`
+fn run(command: Cli, project_dir: Option<PathBuf>) -> HolochainResult<Value> {
+    let project_root = || Project::root(project_dir.as_ref());
 
-    match args {
-        Cli::Web { port } => cli::web(port).or_else(|err| Err(HolochainError::Default(err)))?,
-        Cli::Agent => cli::agent().or_else(|err| Err(HolochainError::Default(err)))?,
-        Cli::Package { strip_meta, output } => {
-            cli::package(strip_meta, output).or_else(|err| Err(HolochainError::Default(err)))?
+    let result = match command {
+        Cli::Web { port } => {
+            cli::web(port)?;
+            json!({})
         }
-        Cli::Unpack { path, to } => {
-            cli::unpack(&path, &to).or_else(|err| Err(HolochainError::Default(err)))?
+        Cli::Agent => {
+            cli::agent()?;
+            json!({})
         }
-        Cli::Init { path } => {
-            cli::init(&path).or_else(|err| Err(HolochainError::Default(err)))?
+        Cli::Doctor => json!({ "checks": cli::doctor(&project_root()?)? }),
+        Cli::Package { strip_meta, bundle, fuel, max_memory_pages, timeout, verbose, profile } => {
+            let project_root = project_root()?;
+            let mut limits = Project::load(&project_root)?.package.limits;
+
+            if fuel.is_some() {
+                limits.fuel = fuel;
+            }
+
+            if let Some(max_memory_pages) = max_memory_pages {
+                limits.max_memory_pages = max_memory_pages;
+            }
+
+            if let Some(timeout) = timeout {
+                limits.timeout_secs = timeout;
+            }
+
+            json!(cli::package(&project_root, strip_meta, bundle, &limits, verbose, &profile)?)
         }
-        Cli::Generate { zome, language } => {
-            cli::generate(&zome, &language).or_else(|err| Err(HolochainError::Default(err)))?
+        Cli::Unpack { path, to } => json!({ "created": cli::unpack(&path, &to)? }),
+        Cli::Init { path, template, name, authors, description, version, zomes, yes, merge, no_workspace } => {
+            let mut details = cli::AppDetails { name, authors, description, version, zomes };
+
+            // fail before asking, rather than after all the questions were answered
+            cli::check_init_target(&path, merge)?;
+
+            // only ask when there is someone to answer
+            if !yes && atty::is(atty::Stream::Stdin) {
+                let stdin = io::stdin();
+                details = details.prompt(&path, &mut stdin.lock())?;
+            }
+
+            json!(cli::init(&path, template.as_ref().map(String::as_str), &details, merge, !no_workspace)?)
         }
-        Cli::Test { dir, testfile, skip_npm, skip_build }=> {
-            let tests_folder = dir.unwrap_or(cli::TEST_DIR_NAME.to_string());
-            // this "magic string" comes from the webpack config
-            // in the js-tests-scaffold: https://github.com/holochain/js-tests-scaffold/blob/master/webpack.config.js#L5-L8
-            // they need to stay in sync
-            let test_file = testfile.unwrap_or("test/dist/bundle.js".to_string());
-            cli::test(&PathBuf::new().join("."), &tests_folder, &test_file, skip_npm, skip_build).or_else(|err| Err(HolochainError::Default(err)))?
+        Cli::Generate { list_languages: true, .. } => json!({ "languages": cli::list_languages()? }),
+        Cli::Generate { zome, language, name, install, item, .. } => match item {
+            Some(GenerateItem::Function { zome, name, capability, inputs, returns }) => {
+                cli::generate_function(&project_root()?, &zome, &name, &capability, &inputs, returns.as_ref().map(String::as_str))?;
+                json!({ "zome": zome, "function": name, "capability": capability })
+            }
+            Some(GenerateItem::Entry { zome, name, fields }) => {
+                cli::generate_entry(&project_root()?, &zome, &name, &fields)?;
+                json!({ "zome": zome, "entry": name })
+            }
+            None => {
+                let zome = zome.ok_or_else(|| HolochainError::Default(format_err!("the path of the zome to generate is required")))?;
+                cli::generate(&zome, &language, name.as_ref().map(String::as_str), install)?;
+                json!({ "zome": zome, "language": language })
+            }
+        },
+        Cli::Test { dir, testfile, skip_npm, skip_build, filter, zome, list, coverage, coverage_threshold, profile } => {
+            let project_path = project_root()?;
+            let mut test_config = Project::load(&project_path)?.test;
+
+            if let Some(dir) = dir {
+                test_config.dir = dir;
+            }
+
+            if let Some(testfile) = testfile {
+                test_config.artifact = Some(PathBuf::from(testfile));
+            }
+
+            if profile.is_some() {
+                test_config.profile = profile;
+            }
+
+            let selection = cli::TestSelection { filter, zome };
+            let coverage_threshold = coverage_threshold.or(if coverage { Some(0.0) } else { None });
+
+            if list {
+                json!({ "tests": cli::list_tests(&project_path, &test_config, &selection, skip_npm)? })
+            } else {
+                cli::test(&project_path, &test_config, &selection, skip_npm, skip_build, coverage_threshold)?;
+                json!({})
+            }
         }
-    }
+    };
 
-    Ok(())
+    Ok(result)
 }
@@ -0,0 +1,660 @@
+use error::DefaultResult;
+use parity_wasm::elements::{External, MemoryType, Module};
+use pwasm_utils::{self, rules};
+use serde_json::{self, Map, Value};
+use std::{
+    cell::RefCell,
+    fmt,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc::{self, RecvTimeoutError},
+        Arc, Mutex,
+    },
+    thread,
+    time::Duration,
+};
+use wasm::{self, JSON_DEFINITION_EXPORT, MEMORY_EXPORT};
+use wasmi::{
+    self, memory_units::Pages, Error as WasmiError, ExternVal, Externals, FuncInstance, FuncRef,
+    GlobalDescriptor, GlobalRef, HostError, ImportResolver, MemoryDescriptor, MemoryInstance,
+    MemoryRef, ModuleInstance, RuntimeArgs, RuntimeValue, Signature, TableDescriptor,
+    TableInstance, TableRef, Trap, TrapKind, ValueType,
+};
+
+/// The input passed to `__hdk_get_json_definition`
+const DEFINITION_INPUT: &[u8] = b"{}";
+
+/// The host function metering the zome, injected by `pwasm_utils::inject_gas_counter`
+const GAS_FUNCTION: &str = "env.gas";
+
+/// The host function the HDK passes debug messages to, which get captured
+const DEBUG_FUNCTION: &str = "env.hc_debug";
+
+/// Bounds for running zome code during packaging, configured in the `package` section
+/// of the project config
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(default)]
+pub struct Limits {
+    /// How many instructions the call may execute, or no limit if `None`
+    pub fuel: Option<u64>,
+    /// How many pages of 64 KiB the memory of the zome may grow to
+    pub max_memory_pages: u32,
+    /// How many seconds the call may take
+    pub timeout_secs: u64,
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Limits {
+            fuel: Some(100_000_000),
+            max_memory_pages: 256,
+            timeout_secs: 10,
+        }
+    }
+}
+
+impl Limits {
+    fn timeout(&self) -> Duration {
+        Duration::from_secs(self.timeout_secs)
+    }
+}
+
+/// Raised when the zome calls into Holochain, none of which is needed to return the definition
+#[derive(Debug)]
+struct HostFunctionUnavailable(String);
+
+impl fmt::Display for HostFunctionUnavailable {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "the zome called the host function {}, which isn't available while packaging",
+            self.0
+        )
+    }
+}
+
+impl HostError for HostFunctionUnavailable {}
+
+/// Raised when the zome executed more instructions than its fuel allows
+#[derive(Debug)]
+struct FuelExhausted(u64);
+
+impl fmt::Display for FuelExhausted {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "the zome ran out of fuel after executing {} instructions (see --fuel)",
+            self.0
+        )
+    }
+}
+
+impl HostError for FuelExhausted {}
+
+/// Raised when the zome is still running at the timeout, to stop the thread running it
+#[derive(Debug)]
+struct TimedOut;
+
+impl fmt::Display for TimedOut {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "the zome was stopped at the timeout")
+    }
+}
+
+impl HostError for TimedOut {}
+
+/// The messages a zome passed to `hc_debug`, shared with the thread running it so that
+/// they're available even if it doesn't return
+#[derive(Clone, Default)]
+pub struct Log(Arc<Mutex<Vec<String>>>);
+
+impl Log {
+    fn push(&self, message: String) {
+        match self.0.lock() {
+            Ok(mut messages) => messages.push(message),
+            Err(poisoned) => poisoned.into_inner().push(message),
+        }
+    }
+
+    /// The messages captured so far
+    pub fn messages(&self) -> Vec<String> {
+        match self.0.lock() {
+            Ok(messages) => messages.clone(),
+            Err(poisoned) => poisoned.into_inner().clone(),
+        }
+    }
+}
+
+struct HostFunction {
+    name: String,
+    return_type: Option<ValueType>,
+}
+
+/// Satisfies all imports of the zome: host functions with stubs that fail when called,
+/// memories and tables with fresh ones. Globals can't be imported.
+#[derive(Default)]
+struct PackagingImports {
+    functions: RefCell<Vec<HostFunction>>,
+}
+
+impl ImportResolver for PackagingImports {
+    fn resolve_func(
+        &self,
+        module_name: &str,
+        field_name: &str,
+        signature: &Signature,
+    ) -> Result<FuncRef, WasmiError> {
+        let mut functions = self.functions.borrow_mut();
+        functions.push(HostFunction {
+            name: format!("{}.{}", module_name, field_name),
+            return_type: signature.return_type(),
+        });
+
+        Ok(FuncInstance::alloc_host(signature.clone(), functions.len() - 1))
+    }
+
+    fn resolve_global(
+        &self,
+        module_name: &str,
+        field_name: &str,
+        _descriptor: &GlobalDescriptor,
+    ) -> Result<GlobalRef, WasmiError> {
+        Err(WasmiError::Instantiation(format!(
+            "the global {}.{} can't be imported while packaging",
+            module_name, field_name
+        )))
+    }
+
+    fn resolve_memory(
+        &self,
+        _module_name: &str,
+        _field_name: &str,
+        descriptor: &MemoryDescriptor,
+    ) -> Result<MemoryRef, WasmiError> {
+        MemoryInstance::alloc(
+            Pages(descriptor.initial() as usize),
+            descriptor.maximum().map(|maximum| Pages(maximum as usize)),
+        )
+    }
+
+    fn resolve_table(
+        &self,
+        _module_name: &str,
+        _field_name: &str,
+        descriptor: &TableDescriptor,
+    ) -> Result<TableRef, WasmiError> {
+        TableInstance::alloc(descriptor.initial(), descriptor.maximum())
+    }
+}
+
+struct PackagingExternals {
+    functions: Vec<HostFunction>,
+    memory: MemoryRef,
+    log: Log,
+    /// The fuel of the zome, or no limit if `None`
+    fuel: Option<u64>,
+    fuel_used: u64,
+    /// Set at the timeout, which stops the zome the next time it is charged for fuel
+    timed_out: Arc<AtomicBool>,
+}
+
+impl PackagingExternals {
+    /// Reads the message passed to `hc_debug`, a JSON string like all inputs from the HDK
+    fn debug_message(&self, args: &RuntimeArgs) -> String {
+        args.nth_value_checked(0)
+            .ok()
+            .and_then(|encoded| decode_allocation(encoded).ok())
+            .and_then(|(offset, length)| self.memory.get(offset, length as usize).ok())
+            .map(|bytes| {
+                serde_json::from_slice(&bytes)
+                    .unwrap_or_else(|_| String::from_utf8_lossy(&bytes).into_owned())
+            })
+            .unwrap_or_else(|| "(unreadable message)".into())
+    }
+}
+
+impl Externals for PackagingExternals {
+    fn invoke_index(&mut self, index: usize, args: RuntimeArgs) -> Result<Option<RuntimeValue>, Trap> {
+        if self.functions[index].name == DEBUG_FUNCTION {
+            let message = self.debug_message(&args);
+            self.log.push(message);
+
+            // report success, which is zero for all host functions
+            return Ok(self.functions[index].return_type.map(RuntimeValue::default));
+        }
+
+        if self.functions[index].name == GAS_FUNCTION {
+            if self.timed_out.load(Ordering::Relaxed) {
+                return Err(Trap::new(TrapKind::Host(Box::new(TimedOut))));
+            }
+
+            let amount: u32 = args.nth_checked(0)?;
+            self.fuel_used += u64::from(amount);
+
+            if let Some(fuel) = self.fuel {
+                if self.fuel_used > fuel {
+                    return Err(Trap::new(TrapKind::Host(Box::new(FuelExhausted(fuel)))));
+                }
+            }
+
+            return Ok(None);
+        }
+
+        Err(Trap::new(TrapKind::Host(Box::new(HostFunctionUnavailable(
+            self.functions[index].name.clone(),
+        )))))
+    }
+}
+
+/// Describes why running the zome failed, with the message of our own host errors
+fn describe_trap(trap: &Trap) -> String {
+    match trap.kind() {
+        TrapKind::Host(host_error) => host_error.to_string(),
+        kind => format!("{:?}", kind),
+    }
+}
+
+fn describe_error(err: &WasmiError) -> String {
+    match err {
+        WasmiError::Trap(trap) => describe_trap(trap),
+        err => err.to_string(),
+    }
+}
+
+/// Lowers the maximum of all memories of the module to the limit, so that growing them
+/// any further fails inside the zome
+fn cap_memory(module: &mut Module, max_pages: u32) -> DefaultResult<()> {
+    let cap = |memory_type: &mut MemoryType| -> DefaultResult<()> {
+        let initial = memory_type.limits().initial();
+        let maximum = memory_type.limits().maximum();
+
+        ensure!(
+            initial <= max_pages,
+            "the zome needs {} pages of memory to start, more than the limit of {} (see --max-memory-pages)",
+            initial,
+            max_pages
+        );
+
+        *memory_type = MemoryType::new(
+            initial,
+            Some(maximum.map_or(max_pages, |maximum| maximum.min(max_pages))),
+        );
+        Ok(())
+    };
+
+    if let Some(section) = module.memory_section_mut() {
+        for memory_type in section.entries_mut() {
+            cap(memory_type)?;
+        }
+    }
+
+    if let Some(section) = module.import_section_mut() {
+        for entry in section.entries_mut() {
+            if let External::Memory(ref mut memory_type) = *entry.external_mut() {
+                cap(memory_type)?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Splits the encoded allocation returned by a zome function into offset and length
+fn decode_allocation(encoded: RuntimeValue) -> DefaultResult<(u32, u32)> {
+    match encoded {
+        RuntimeValue::I32(encoded) => {
+            let encoded = encoded as u32;
+            Ok((encoded >> 16, encoded & 0xffff))
+        }
+        RuntimeValue::I64(encoded) => {
+            let encoded = encoded as u64;
+            Ok(((encoded >> 32) as u32, encoded as u32))
+        }
+        other => bail!(
+            "{} returned {:?} instead of an encoded allocation",
+            JSON_DEFINITION_EXPORT,
+            other
+        ),
+    }
+}
+
+fn call_definition(
+    wasm: &[u8],
+    limits: &Limits,
+    log: Log,
+    timed_out: Arc<AtomicBool>,
+) -> DefaultResult<Map<String, Value>> {
+    let mut module = wasm::parse_module(wasm)?;
+    cap_memory(&mut module, limits.max_memory_pages)?;
+
+    // every instruction costs one unit of fuel, charged block by block, which also gives
+    // the zome a chance to notice the timeout even without a limit on fuel
+    let module = pwasm_utils::inject_gas_counter(module, &rules::Set::default())
+        .map_err(|_| format_err!("unable to meter the zome"))?;
+
+    let module = wasmi::Module::from_parity_wasm_module(module)
+        .map_err(|err| format_err!("invalid WASM module: {}", err))?;
+
+    let imports = PackagingImports::default();
+    let instance = ModuleInstance::new(&module, &imports)
+        .map_err(|err| format_err!("unable to instantiate the zome: {}", err))?;
+
+    let memory = match instance.not_started_instance().export_by_name(MEMORY_EXPORT) {
+        Some(ExternVal::Memory(memory)) => memory,
+        _ => bail!("the zome doesn't export its {}", MEMORY_EXPORT),
+    };
+
+    let mut externals = PackagingExternals {
+        functions: imports.functions.into_inner(),
+        memory: memory.clone(),
+        log,
+        fuel: limits.fuel,
+        fuel_used: 0,
+        timed_out,
+    };
+    let instance = instance
+        .run_start(&mut externals)
+        .map_err(|trap| format_err!("the start function of the zome failed: {}", describe_trap(&trap)))?;
+    let definition_function = match instance.export_by_name(JSON_DEFINITION_EXPORT) {
+        Some(ExternVal::Func(function)) => function,
+        _ => bail!("the zome doesn't export the function {}", JSON_DEFINITION_EXPORT),
+    };
+
+    // like the ribosome, write the input to the start of the memory, which makes its
+    // encoded allocation just its length
+    memory
+        .set(0, DEFINITION_INPUT)
+        .map_err(|err| format_err!("unable to pass the input: {}", err))?;
+
+    let input = match definition_function.signature().params() {
+        [ValueType::I32] => RuntimeValue::I32(DEFINITION_INPUT.len() as i32),
+        [ValueType::I64] => RuntimeValue::I64(DEFINITION_INPUT.len() as i64),
+        params => bail!(
+            "{} takes {:?}, instead of an encoded allocation",
+            JSON_DEFINITION_EXPORT,
+            params
+        ),
+    };
+
+    let result = instance
+        .invoke_export(JSON_DEFINITION_EXPORT, &[input], &mut externals)
+        .map_err(|err| {
+            // a failed allocation usually ends in a trap of the zome's own, so name the
+            // likely cause when there is no memory left to grow into
+            let memory_exhausted = memory.current_size().0 as u32 >= limits.max_memory_pages;
+
+            format_err!(
+                "calling {} failed: {}{}",
+                JSON_DEFINITION_EXPORT,
+                describe_error(&err),
+                if memory_exhausted {
+                    format!(
+                        ", after the zome used up its memory limit of {} pages (see --max-memory-pages)",
+                        limits.max_memory_pages
+                    )
+                } else {
+                    String::new()
+                }
+            )
+        })?
+        .ok_or_else(|| format_err!("{} didn't return anything", JSON_DEFINITION_EXPORT))?;
+
+    let (offset, length) = decode_allocation(result)?;
+
+    // allocations at the very start of the memory are error codes
+    ensure!(
+        offset != 0,
+        "{} returned the error code {}",
+        JSON_DEFINITION_EXPORT,
+        length
+    );
+
+    let json = memory
+        .get(offset, length as usize)
+        .map_err(|err| format_err!("unable to read the definition: {}", err))?;
+
+    serde_json::from_slice(&json)
+        .map_err(|err| format_err!("the definition returned by the zome isn't a JSON object: {}", err))
+}
+
+/// Calls `__hdk_get_json_definition` of a zome, with nothing but stubs for the host
+/// functions, within the given limits. The debug messages of the zome end up in `log`.
+pub fn extract_definition(wasm: Vec<u8>, limits: &Limits, log: &Log) -> DefaultResult<Map<String, Value>> {
+    let (sender, receiver) = mpsc::channel();
+    let call_limits = limits.clone();
+    let call_log = log.clone();
+    let timed_out = Arc::new(AtomicBool::new(false));
+    let call_timed_out = timed_out.clone();
+
+    let call = thread::spawn(move || {
+        let _ = sender.send(call_definition(&wasm, &call_limits, call_log, call_timed_out));
+    });
+
+    match receiver.recv_timeout(limits.timeout()) {
+        Ok(result) => result,
+        Err(RecvTimeoutError::Timeout) => {
+            // the zome traps the next time it is charged for fuel, which ends the thread
+            timed_out.store(true, Ordering::Relaxed);
+            let _ = call.join();
+
+            bail!(
+                "{} didn't return within {} seconds (see --timeout)",
+                JSON_DEFINITION_EXPORT,
+                limits.timeout_secs
+            )
+        }
+        Err(RecvTimeoutError::Disconnected) => bail!("{} crashed", JSON_DEFINITION_EXPORT),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use parity_wasm::{
+        builder,
+        elements::{self, Instruction, Instructions},
+    };
+
+    const DEFINITION: &str = r#"{"entry_types":{},"capabilities":{}}"#;
+    const DEFINITION_OFFSET: i32 = 1024;
+    const MESSAGE: &str = r#""hello from the zome""#;
+    const MESSAGE_OFFSET: i32 = 2048;
+
+    /// A zome whose definition function runs the given instructions, which can call
+    /// `hc_debug` as function 0 and `hc_commit_entry` as function 1
+    fn zome(instructions: Vec<Instruction>) -> Vec<u8> {
+        let module = builder::module()
+            .import()
+            .module("env")
+            .field("hc_debug")
+            .external()
+            .func(0)
+            .build()
+            .import()
+            .module("env")
+            .field("hc_commit_entry")
+            .external()
+            .func(0)
+            .build()
+            .function()
+            .signature().with_param(elements::ValueType::I32).with_return_type(Some(elements::ValueType::I32)).build()
+            .body().with_instructions(Instructions::new(instructions)).build()
+            .build()
+            .memory().with_min(1).build()
+            .data()
+            .offset(Instruction::I32Const(DEFINITION_OFFSET))
+            .value(DEFINITION.as_bytes().to_vec())
+            .build()
+            .data()
+            .offset(Instruction::I32Const(MESSAGE_OFFSET))
+            .value(MESSAGE.as_bytes().to_vec())
+            .build()
+            .export().field(JSON_DEFINITION_EXPORT).internal().func(2).build()
+            .export().field(MEMORY_EXPORT).internal().memory(0).build()
+            .build();
+
+        elements::serialize(module).unwrap()
+    }
+
+    fn short_limits() -> Limits {
+        Limits {
+            timeout_secs: 1,
+            ..Limits::default()
+        }
+    }
+
+    /// A definition function that never returns
+    fn endless_zome() -> Vec<u8> {
+        zome(vec![
+            Instruction::Loop(elements::BlockType::NoResult),
+            Instruction::Br(0),
+            Instruction::End,
+            Instruction::I32Const(0),
+            Instruction::End,
+        ])
+    }
+
+    #[test]
+    fn extracts_definition() {
+        let encoded = (DEFINITION_OFFSET << 16) | DEFINITION.len() as i32;
+        let wasm = zome(vec![Instruction::I32Const(encoded), Instruction::End]);
+
+        let definition = extract_definition(wasm, &short_limits(), &Log::default()).unwrap();
+
+        assert_eq!(Value::from(definition), serde_json::from_str::<Value>(DEFINITION).unwrap());
+    }
+
+    #[test]
+    fn host_functions_are_unavailable() {
+        let wasm = zome(vec![
+            Instruction::GetLocal(0),
+            Instruction::Call(1),
+            Instruction::End,
+        ]);
+
+        let err = extract_definition(wasm, &short_limits(), &Log::default()).unwrap_err();
+
+        assert_eq!(
+            err.to_string(),
+            "calling __hdk_get_json_definition failed: the zome called the host function \
+             env.hc_commit_entry, which isn't available while packaging"
+        );
+    }
+
+    #[test]
+    fn captures_debug_messages() {
+        let message = (MESSAGE_OFFSET << 16) | MESSAGE.len() as i32;
+        let encoded = (DEFINITION_OFFSET << 16) | DEFINITION.len() as i32;
+        let wasm = zome(vec![
+            Instruction::I32Const(message),
+            Instruction::Call(0),
+            Instruction::Drop,
+            Instruction::I32Const(encoded),
+            Instruction::End,
+        ]);
+        let log = Log::default();
+
+        extract_definition(wasm, &short_limits(), &log).unwrap();
+
+        assert_eq!(log.messages(), vec!["hello from the zome".to_string()]);
+
+        // the messages logged before a failure are kept
+        let wasm = zome(vec![
+            Instruction::I32Const(message),
+            Instruction::Call(0),
+            Instruction::Call(1),
+            Instruction::End,
+        ]);
+        let log = Log::default();
+
+        assert!(extract_definition(wasm, &short_limits(), &log).is_err());
+        assert_eq!(log.messages(), vec!["hello from the zome".to_string()]);
+    }
+
+    #[test]
+    fn stops_at_the_timeout() {
+        let limits = Limits {
+            fuel: None,
+            ..short_limits()
+        };
+
+        // a zome that keeps logging, so that it's visible whether it still runs
+        let message = (MESSAGE_OFFSET << 16) | MESSAGE.len() as i32;
+        let wasm = zome(vec![
+            Instruction::Loop(elements::BlockType::NoResult),
+            Instruction::I32Const(message),
+            Instruction::Call(0),
+            Instruction::Drop,
+            Instruction::Br(0),
+            Instruction::End,
+            Instruction::I32Const(0),
+            Instruction::End,
+        ]);
+        let log = Log::default();
+
+        let err = extract_definition(wasm, &limits, &log).unwrap_err();
+
+        assert!(err.to_string().contains("didn't return within 1 seconds"));
+
+        // the zome was stopped rather than left running in the background
+        let logged = log.messages().len();
+        thread::sleep(Duration::from_millis(100));
+        assert_eq!(log.messages().len(), logged);
+    }
+
+    #[test]
+    fn stops_when_out_of_fuel() {
+        let limits = Limits {
+            fuel: Some(1000),
+            timeout_secs: 60,
+            ..Limits::default()
+        };
+
+        let err = extract_definition(endless_zome(), &limits, &Log::default()).unwrap_err();
+
+        assert_eq!(
+            err.to_string(),
+            "calling __hdk_get_json_definition failed: the zome ran out of fuel after \
+             executing 1000 instructions (see --fuel)"
+        );
+
+        // a zome that returns right away gets by with little fuel
+        let encoded = (DEFINITION_OFFSET << 16) | DEFINITION.len() as i32;
+        let wasm = zome(vec![Instruction::I32Const(encoded), Instruction::End]);
+
+        assert!(extract_definition(wasm, &limits, &Log::default()).is_ok());
+    }
+
+    #[test]
+    fn names_the_memory_limit() {
+        // grows the memory as far as it goes, then traps like a failed allocation
+        let wasm = zome(vec![
+            Instruction::I32Const(1000),
+            Instruction::GrowMemory(0),
+            Instruction::Drop,
+            Instruction::I32Const(1),
+            Instruction::GrowMemory(0),
+            Instruction::Drop,
+            Instruction::Unreachable,
+            Instruction::End,
+        ]);
+        let limits = Limits {
+            max_memory_pages: 2,
+            ..short_limits()
+        };
+
+        let err = extract_definition(wasm, &limits, &Log::default()).unwrap_err();
+
+        assert!(err.to_string().contains("memory limit of 2 pages"));
+    }
+
+    #[test]
+    fn caps_memory() {
+        let mut module = wasm::parse_module(&zome(vec![Instruction::I32Const(0), Instruction::End])).unwrap();
+
+        cap_memory(&mut module, 4).unwrap();
+        assert_eq!(module.memory_section().unwrap().entries()[0].limits().maximum(), Some(4));
+
+        assert!(cap_memory(&mut module, 0).is_err());
+    }
+}
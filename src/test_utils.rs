@@ -0,0 +1,11 @@
+use tempfile::{Builder, TempDir};
+
+pub const HOLOCHAIN_TEST_PREFIX: &str = "org.holochain.test";
+
+/// A temporary folder for a test, removed when it's dropped
+pub fn gen_dir() -> TempDir {
+    Builder::new()
+        .prefix(HOLOCHAIN_TEST_PREFIX)
+        .tempdir()
+        .unwrap()
+}
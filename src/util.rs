@@ -1,26 +1,280 @@
 use colored::*;
-use error::DefaultResult;
-use std::{path::PathBuf, process::Command};
+use dirs;
+use error::{BuildStepError, CapturedStep, DefaultResult, HolochainError};
+use std::{
+    collections::VecDeque,
+    env,
+    fs::{self, OpenOptions},
+    io::{self, BufRead, BufReader, Read, Write},
+    path::{Path, PathBuf},
+    process::{Command, ExitStatus, Stdio},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc::{self, Sender},
+    },
+    thread::{self, JoinHandle},
+    time::{Duration, Instant},
+};
+
+/// How many of the last lines of stderr are shown when a build step fails
+const STDERR_TAIL_LINES: usize = 20;
+
+/// Whether the result of the command gets printed as JSON, see `--output`
+static JSON_OUTPUT: AtomicBool = AtomicBool::new(false);
+
+pub fn set_json_output(json_output: bool) {
+    JSON_OUTPUT.store(json_output, Ordering::SeqCst);
+}
+
+pub fn json_output() -> bool {
+    JSON_OUTPUT.load(Ordering::SeqCst)
+}
+
+/// Prints a message for humans, which goes to stderr when stdout is reserved for the
+/// JSON result
+macro_rules! log {
+    ($($arg:tt)*) => {
+        if ::util::json_output() {
+            eprintln!($($arg)*);
+        } else {
+            println!($($arg)*);
+        }
+    };
+}
 
 pub fn run_cmd(base_path: PathBuf, bin: String, args: Vec<String>) -> DefaultResult<()> {
+    run_cmd_with_env(base_path, bin, args, &[])
+}
+
+/// Same as `run_cmd`, but with additional environment variables set for the command
+pub fn run_cmd_with_env(
+    base_path: PathBuf,
+    bin: String,
+    args: Vec<String>,
+    env: &[(String, String)],
+) -> DefaultResult<()> {
+    let pretty_command = format!("{} {}", bin.green(), args.join(" ").cyan());
+    let command = format!("{} {}", bin, args.join(" ")).trim_end().to_string();
+
+    log!("> {}", pretty_command);
+
+    let mut child = Command::new(&bin)
+        .args(args)
+        .envs(env.iter().cloned())
+        .current_dir(base_path)
+        // keep stdout free for the JSON result
+        .stdout(if json_output() { Stdio::piped() } else { Stdio::inherit() })
+        .spawn()
+        .map_err(|err| io::Error::new(err.kind(), format!("unable to run {}: {}", bin, err)))?;
+
+    if let Some(ref mut stdout) = child.stdout {
+        io::copy(stdout, &mut io::stderr())?;
+    }
+
+    check_status(command, child.wait()?)
+}
+
+/// Same as `run_cmd_with_env`, but each line the command prints to stdout is passed to the
+/// filter first, and only shown if the filter returns true
+pub fn run_cmd_filtering_stdout<F: FnMut(&str) -> bool>(
+    base_path: PathBuf,
+    bin: String,
+    args: Vec<String>,
+    env: &[(String, String)],
+    mut filter: F,
+) -> DefaultResult<()> {
     let pretty_command = format!("{} {}", bin.green(), args.join(" ").cyan());
+    let command = format!("{} {}", bin, args.join(" ")).trim_end().to_string();
 
-    println!("> {}", pretty_command);
+    log!("> {}", pretty_command);
 
-    let status = Command::new(bin)
+    let mut child = Command::new(&bin)
         .args(args)
+        .envs(env.iter().cloned())
         .current_dir(base_path)
-        .status()?;
+        .stdout(Stdio::piped())
+        .spawn()
+        .map_err(|err| io::Error::new(err.kind(), format!("unable to run {}: {}", bin, err)))?;
+
+    if let Some(stdout) = child.stdout.take() {
+        for line in BufReader::new(stdout).lines() {
+            let line = line?;
+
+            if filter(&line) {
+                log!("{}", line);
+            }
+        }
+    }
+
+    check_status(command, child.wait()?)
+}
 
-    ensure!(
-        status.success(),
-        "command {} was not successful",
-        pretty_command
-    );
+fn check_status(command: String, status: ExitStatus) -> DefaultResult<()> {
+    if !status.success() {
+        return Err(HolochainError::BuildStep(BuildStepError {
+            command,
+            exit_code: status.code(),
+            captured: None,
+        }).into());
+    }
 
     Ok(())
 }
 
+#[derive(Clone, Copy, PartialEq)]
+enum OutputStream {
+    Stdout,
+    Stderr,
+}
+
+/// Sends the lines read from the output of a child process to the given channel
+fn forward_lines<R: Read + Send + 'static>(
+    output: R,
+    stream: OutputStream,
+    sender: Sender<(OutputStream, String)>,
+) -> JoinHandle<()> {
+    thread::spawn(move || {
+        let mut reader = BufReader::new(output);
+        let mut line = Vec::new();
+
+        while let Ok(read) = reader.read_until(b'\n', &mut line) {
+            if read == 0 {
+                break;
+            }
+
+            let text = String::from_utf8_lossy(&line).trim_end_matches(&['\r', '\n'][..]).to_string();
+
+            if sender.send((stream, text)).is_err() {
+                break;
+            }
+
+            line.clear();
+        }
+    })
+}
+
+/// Runs a build step of a zome. Its output is shown live, prefixed with the name of the zome,
+/// and appended to the log file. Returns how long the step took, or fails with the tail of
+/// its stderr.
+pub fn run_build_step(
+    base_path: &Path,
+    bin: &str,
+    args: &[String],
+    env: &[(String, String)],
+    zome: &str,
+    log_file: &Path,
+) -> DefaultResult<Duration> {
+    let command = format!("{} {}", bin, args.join(" ")).trim_end().to_string();
+
+    log!("> {} {}", bin.green(), args.join(" ").cyan());
+
+    if let Some(log_dir) = log_file.parent() {
+        fs::create_dir_all(log_dir)?;
+    }
+
+    let mut log = OpenOptions::new().create(true).append(true).open(log_file)?;
+    writeln!(log, "> {}", command)?;
+
+    let started = Instant::now();
+
+    let mut child = Command::new(bin)
+        .args(args)
+        .envs(env.iter().cloned())
+        .current_dir(base_path)
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|err| io::Error::new(err.kind(), format!("unable to run {}: {}", bin, err)))?;
+
+    let (sender, receiver) = mpsc::channel();
+    let readers = vec![
+        forward_lines(child.stdout.take().unwrap(), OutputStream::Stdout, sender.clone()),
+        forward_lines(child.stderr.take().unwrap(), OutputStream::Stderr, sender),
+    ];
+
+    let prefix = format!("{} |", zome).cyan();
+    let mut stderr_tail = VecDeque::with_capacity(STDERR_TAIL_LINES);
+
+    // ends once both streams are closed
+    for (stream, line) in receiver {
+        writeln!(log, "{}", line)?;
+
+        if stream == OutputStream::Stdout {
+            log!("{} {}", prefix, line);
+        } else {
+            eprintln!("{} {}", prefix, line);
+
+            if stderr_tail.len() == STDERR_TAIL_LINES {
+                stderr_tail.pop_front();
+            }
+            stderr_tail.push_back(line);
+        }
+    }
+
+    for reader in readers {
+        let _ = reader.join();
+    }
+
+    let status = child.wait()?;
+    let duration = started.elapsed();
+
+    writeln!(log, "{} after {:.2}s", status, seconds(duration))?;
+
+    if !status.success() {
+        return Err(HolochainError::BuildStep(BuildStepError {
+            command,
+            exit_code: status.code(),
+            captured: Some(CapturedStep {
+                zome: zome.to_string(),
+                duration,
+                log_file: log_file.to_path_buf(),
+                stderr_tail: stderr_tail.into_iter().collect(),
+            }),
+        }).into());
+    }
+
+    Ok(duration)
+}
+
+/// A duration in seconds, for showing it with a fraction
+pub fn seconds(duration: Duration) -> f64 {
+    duration.as_secs() as f64 + f64::from(duration.subsec_millis()) / 1000.0
+}
+
+/// Encodes bytes, like a hash, as lowercase hex
+pub fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+#[cfg(unix)]
+pub fn is_executable(path: &Path) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+
+    path.metadata()
+        .map(|metadata| metadata.is_file() && metadata.permissions().mode() & 0o111 != 0)
+        .unwrap_or(false)
+}
+
+#[cfg(not(unix))]
+pub fn is_executable(path: &Path) -> bool {
+    path.is_file()
+}
+
+/// Finds the executable a command runs: a path is taken relative to the base path, a plain
+/// name is looked up on the PATH
+pub fn find_executable(base_path: &Path, bin: &str) -> Option<PathBuf> {
+    if Path::new(bin).components().count() > 1 {
+        let path = base_path.join(bin);
+        return if is_executable(&path) { Some(path) } else { None };
+    }
+
+    let paths = env::var_os("PATH").unwrap_or_default();
+
+    env::split_paths(&paths)
+        .map(|dir| dir.join(format!("{}{}", bin, env::consts::EXE_SUFFIX)))
+        .find(|path| is_executable(path))
+}
+
 /// Helper method for obtaining the file name of a path as a String
 pub fn file_name_string(path: &PathBuf) -> DefaultResult<String> {
     let file_name = path
@@ -31,3 +285,40 @@ pub fn file_name_string(path: &PathBuf) -> DefaultResult<String> {
 
     Ok(file_name.into())
 }
+
+/// Returns the folder holding the user's hc configuration, `~/.config/hc`
+pub fn config_dir() -> DefaultResult<PathBuf> {
+    let home_dir =
+        dirs::home_dir().ok_or_else(|| format_err!("unable to determine the home directory"))?;
+
+    Ok(home_dir.join(".config").join("hc"))
+}
+
+/// Asks a question on the terminal and reads the answer from the given input. Returns the
+/// default if the answer is empty or the input has ended.
+pub fn prompt<R: BufRead>(input: &mut R, question: &str, default: &str) -> DefaultResult<String> {
+    let question = if default.is_empty() {
+        format!("{}: ", question.bold())
+    } else {
+        format!("{} [{}]: ", question.bold(), default)
+    };
+
+    if json_output() {
+        eprint!("{}", question);
+        io::stderr().flush()?;
+    } else {
+        print!("{}", question);
+        io::stdout().flush()?;
+    }
+
+    let mut answer = String::new();
+    input.read_line(&mut answer)?;
+
+    let answer = answer.trim();
+
+    if answer.is_empty() {
+        Ok(default.to_string())
+    } else {
+        Ok(answer.to_string())
+    }
+}
@@ -0,0 +1,216 @@
+use config_files::Optimize;
+use error::DefaultResult;
+use parity_wasm::elements::{self, Internal, Module, Section};
+use pwasm_utils;
+use std::{fs, process::Command};
+use tempfile::Builder;
+use util;
+
+const WASM_OPT_BIN: &str = "wasm-opt";
+
+/// The function Holochain calls to find out about the entry types and functions of a zome
+pub const JSON_DEFINITION_EXPORT: &str = "__hdk_get_json_definition";
+
+/// The memory zome functions pass their inputs and outputs in
+pub const MEMORY_EXPORT: &str = "memory";
+
+pub fn parse_module(wasm: &[u8]) -> DefaultResult<Module> {
+    elements::deserialize_buffer(wasm).map_err(|err| format_err!("not a valid WASM module: {}", err))
+}
+
+/// Checks that the module is well-formed and exports what the HDK requires: the
+/// `__hdk_get_json_definition` function and the memory
+pub fn validate_zome(wasm: &[u8]) -> DefaultResult<()> {
+    let module = parse_module(wasm)?;
+
+    let exports = module
+        .export_section()
+        .map(|section| section.entries())
+        .unwrap_or_default();
+
+    let exports_function = |name: &str| {
+        exports.iter().any(|export| match export.internal() {
+            Internal::Function(_) => export.field() == name,
+            _ => false,
+        })
+    };
+    let exports_memory = exports.iter().any(|export| match export.internal() {
+        Internal::Memory(_) => export.field() == MEMORY_EXPORT,
+        _ => false,
+    });
+
+    let mut missing = Vec::new();
+
+    if !exports_function(JSON_DEFINITION_EXPORT) {
+        missing.push(format!("the function {}", JSON_DEFINITION_EXPORT));
+    }
+    if !exports_memory {
+        missing.push(format!("the {}", MEMORY_EXPORT));
+    }
+
+    ensure!(
+        missing.is_empty(),
+        "doesn't export {}, which the HDK requires",
+        missing.join(" and ")
+    );
+
+    Ok(())
+}
+
+/// Removes the sections which aren't needed to run the module, like the function names
+/// used for debugging
+fn strip_custom_sections(module: &mut Module) {
+    module.sections_mut().retain(|section| match section {
+        Section::Custom(_) | Section::Name(_) | Section::Reloc(_) => false,
+        _ => true,
+    });
+}
+
+/// Removes everything that can't be reached from one of the exports
+fn eliminate_dead_code(module: &mut Module) -> DefaultResult<()> {
+    let exports: Vec<String> = module
+        .export_section()
+        .map(|section| section.entries().iter().map(|entry| entry.field().to_string()).collect())
+        .unwrap_or_default();
+
+    // a module without exports can't be called, so there is nothing to keep
+    if exports.is_empty() {
+        return Ok(());
+    }
+
+    pwasm_utils::optimize(module, exports.iter().map(String::as_str).collect())
+        .map_err(|err| format_err!("dead code elimination failed: {:?}", err))
+}
+
+fn wasm_opt_available() -> bool {
+    Command::new(WASM_OPT_BIN)
+        .arg("--version")
+        .output()
+        .map(|output| output.status.success())
+        .unwrap_or(false)
+}
+
+/// Runs wasm-opt from binaryen on the module, returns None if it isn't installed
+fn run_wasm_opt(wasm: &[u8], args: &[String]) -> DefaultResult<Option<Vec<u8>>> {
+    if !wasm_opt_available() {
+        return Ok(None);
+    }
+
+    let dir = Builder::new().prefix("hc-wasm-opt").tempdir()?;
+    let input_path = dir.path().join("input.wasm");
+    let output_path = dir.path().join("output.wasm");
+
+    fs::write(&input_path, wasm)?;
+
+    let mut wasm_opt_args = vec![
+        input_path.to_string_lossy().into_owned(),
+        "-o".to_owned(),
+        output_path.to_string_lossy().into_owned(),
+    ];
+    wasm_opt_args.extend(args.iter().cloned());
+
+    util::run_cmd(dir.path().to_path_buf(), WASM_OPT_BIN.into(), wasm_opt_args)?;
+
+    Ok(Some(fs::read(&output_path)?))
+}
+
+/// Optimizes a module as configured. wasm-opt runs last, and only if it's on the PATH.
+pub fn optimize(wasm: &[u8], options: &Optimize) -> DefaultResult<Vec<u8>> {
+    let mut module = parse_module(wasm)
+        .map_err(|err| format_err!("{}, or it uses WASM features the optimizer doesn't support", err))?;
+
+    if options.strip_custom_sections {
+        strip_custom_sections(&mut module);
+    }
+
+    if options.dead_code_elimination {
+        eliminate_dead_code(&mut module)?;
+    }
+
+    let optimized = elements::serialize(module)
+        .map_err(|err| format_err!("unable to write the optimized WASM module: {}", err))?;
+
+    match options.wasm_opt {
+        Some(ref args) => match run_wasm_opt(&optimized, args)? {
+            Some(optimized) => Ok(optimized),
+            None => {
+                log!("{} is not installed, skipping it", WASM_OPT_BIN);
+                Ok(optimized)
+            }
+        },
+        None => Ok(optimized),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use parity_wasm::{builder, elements::Instruction};
+
+    /// A module exporting one function, with one more function that is never called
+    fn module() -> Vec<u8> {
+        let module = builder::module()
+            .function()
+            .signature().build()
+            .body().with_instructions(elements::Instructions::new(vec![Instruction::End])).build()
+            .build()
+            .function()
+            .signature().build()
+            .body().with_instructions(elements::Instructions::new(vec![Instruction::Nop, Instruction::End])).build()
+            .build()
+            .export().field(JSON_DEFINITION_EXPORT).internal().func(0).build()
+            .memory().build()
+            .export().field(MEMORY_EXPORT).internal().memory(0).build()
+            .with_section(Section::Custom(elements::CustomSection::new("producers".into(), vec![0; 64])))
+            .build();
+
+        elements::serialize(module).unwrap()
+    }
+
+    #[test]
+    fn optimize_test() {
+        let wasm = module();
+        let options = Optimize {
+            strip_custom_sections: true,
+            dead_code_elimination: true,
+            wasm_opt: None,
+        };
+
+        let optimized = optimize(&wasm, &options).unwrap();
+        let module = parse_module(&optimized).unwrap();
+
+        assert!(optimized.len() < wasm.len());
+        assert_eq!(module.custom_sections().count(), 0);
+        assert_eq!(module.function_section().unwrap().entries().len(), 1);
+        assert_eq!(
+            module.export_section().unwrap().entries()[0].field(),
+            JSON_DEFINITION_EXPORT
+        );
+    }
+
+    #[test]
+    fn validate_zome_test() {
+        assert!(validate_zome(&module()).is_ok());
+
+        let err = validate_zome(b"{\"not\": \"wasm\"}").unwrap_err();
+        assert!(err.to_string().starts_with("not a valid WASM module"));
+
+        let module = builder::module()
+            .function()
+            .signature().build()
+            .body().build()
+            .build()
+            .export().field("genesis").internal().func(0).build()
+            .build();
+
+        assert_eq!(
+            validate_zome(&elements::serialize(module).unwrap()).unwrap_err().to_string(),
+            "doesn't export the function __hdk_get_json_definition and the memory, which the HDK requires"
+        );
+    }
+
+    #[test]
+    fn optimize_rejects_invalid_modules() {
+        assert!(optimize(b"not wasm", &Optimize::default()).is_err());
+    }
+}